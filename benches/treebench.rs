@@ -4,9 +4,12 @@ extern crate bv;
 extern crate fp_succinct_trees_1;
 extern crate id_tree;
 
+use bv::BitVec;
 use criterion::Criterion;
 use criterion::Fun;
 use fp_succinct_trees_1::bp_tree::BPTree;
+use fp_succinct_trees_1::common::label_store::WaveletLabels;
+use fp_succinct_trees_1::common::min_max::MinMax;
 use fp_succinct_trees_1::common::succinct_tree::SuccinctTree;
 use fp_succinct_trees_1::louds_tree::LOUDSTree;
 use id_tree::InsertBehavior::*;
@@ -58,10 +61,10 @@ fn create_bench_idtree(c: &mut Criterion) {
 
 fn compare_from_id_tree(c: &mut Criterion) {
     let louds = Fun::new("LOUDS from IDTree", |b, i| {
-        b.iter(|| LOUDSTree::from_id_tree(create_bench_tree()))
+        b.iter(|| LOUDSTree::<_>::from_id_tree(create_bench_tree()))
     });
     let bp = Fun::new("BP from IDTree", |b, i| {
-        b.iter(|| BPTree::from_id_tree(create_bench_tree()))
+        b.iter(|| BPTree::<_>::from_id_tree(create_bench_tree()))
     });
     c.bench_functions("Create from IDTree", vec![louds, bp], 0);
 }
@@ -98,6 +101,128 @@ fn compare_next_sibling(c: &mut Criterion) {
     c.bench_functions("Compare next_sibling()", vec![louds_fun, bp_fun], 0);
 }
 
+/// Creates a balanced parenthesis sequence of about `len` bits from a fixed pseudo random
+/// walk, so that the excess goes up and down inside of every block.
+fn create_bench_bits(len: u64) -> BitVec<u8> {
+    let mut bits = BitVec::with_capacity(len);
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut excess = 0;
+    while bits.len() + excess < len {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let open = excess == 0 || state >> 63 == 1;
+        bits.push(open);
+        excess = if open { excess + 1 } else { excess - 1 };
+    }
+    for _ in 0..excess {
+        bits.push(false);
+    }
+    bits
+}
+
+/// Finds the matching closing parenthesis by walking all bits one at a time, as a reference
+/// for the heap and byte table accelerated search of `MinMax`.
+fn find_close_linear(bits: &BitVec<u8>, index: u64) -> u64 {
+    let mut excess = 0;
+    for position in index.. {
+        excess += if bits[position] { 1 } else { -1 };
+        if excess == 0 {
+            return position;
+        }
+    }
+    unreachable!()
+}
+
+fn compare_min_max_scans(c: &mut Criterion) {
+    let bits = create_bench_bits(1 << 20);
+    let min_max = MinMax::new(bits.clone(), 1024);
+    let opening: Vec<u64> = (0..bits.len())
+        .filter(|index| bits[*index])
+        .step_by(997)
+        .collect();
+    let positions = opening.clone();
+    let table_fun = Fun::new("byte tables", move |b, _| {
+        b.iter(|| {
+            positions
+                .iter()
+                .map(|index| min_max.find_close(*index).unwrap())
+                .sum::<u64>()
+        })
+    });
+    let linear_fun = Fun::new("linear scan", move |b, _| {
+        b.iter(|| {
+            opening
+                .iter()
+                .map(|index| find_close_linear(&bits, *index))
+                .sum::<u64>()
+        })
+    });
+    c.bench_functions("Compare find_close()", vec![table_fun, linear_fun], 0);
+}
+
+fn bench_min_max_block_ops(c: &mut Criterion) {
+    let min_max = MinMax::new(create_bench_bits(1 << 20), 1024);
+    let last = (1 << 20) - 1;
+    c.bench_function("MinMax excess()", move |b| {
+        b.iter(|| {
+            (0..64)
+                .map(|i| min_max.excess(last - 15 * i).unwrap())
+                .sum::<u64>()
+        })
+    });
+    let min_max = MinMax::new(create_bench_bits(1 << 20), 1024);
+    c.bench_function("MinMax rank_1() and select_1()", move |b| {
+        b.iter(|| {
+            (1..64)
+                .map(|i| min_max.select_1(min_max.rank_1(i * 8191).unwrap()).unwrap())
+                .sum::<u64>()
+        })
+    });
+}
+
+/// A root with 256 children labeled with all bytes in descending order, like a node of a
+/// byte trie.
+fn create_wide_tree() -> Tree<u8> {
+    let mut tree: Tree<u8> = TreeBuilder::new().with_node_capacity(257).build();
+    let root_id: NodeId = tree.insert(Node::new(0), AsRoot).unwrap();
+    for label in (0..=255).rev() {
+        tree.insert(Node::new(label), UnderNode(&root_id)).unwrap();
+    }
+    tree
+}
+
+fn compare_labeled_child(c: &mut Criterion) {
+    let louds: LOUDSTree<u8> = LOUDSTree::from_id_tree(create_wide_tree()).unwrap();
+    let louds_sorted: LOUDSTree<u8> = LOUDSTree::from_id_tree_sorted(create_wide_tree()).unwrap();
+    let louds_wavelet: LOUDSTree<u8, WaveletLabels<u8>> =
+        LOUDSTree::from_id_tree(create_wide_tree()).unwrap();
+    let bp: BPTree<u8> = BPTree::from_id_tree(create_wide_tree()).unwrap();
+    let bp_sorted: BPTree<u8> = BPTree::from_id_tree_sorted(create_wide_tree()).unwrap();
+    let louds_fun = Fun::new("LOUDS", move |b, _| b.iter(|| louds.labeled_child(1, 17)));
+    let louds_sorted_fun = Fun::new("LOUDS sorted", move |b, _| {
+        b.iter(|| louds_sorted.labeled_child(1, 17))
+    });
+    let louds_wavelet_fun = Fun::new("LOUDS wavelet", move |b, _| {
+        b.iter(|| louds_wavelet.labeled_child(1, 17))
+    });
+    let bp_fun = Fun::new("BP", move |b, _| b.iter(|| bp.labeled_child(0, 17)));
+    let bp_sorted_fun = Fun::new("BP sorted", move |b, _| {
+        b.iter(|| bp_sorted.labeled_child(0, 17))
+    });
+    c.bench_functions(
+        "Compare labeled_child()",
+        vec![
+            louds_fun,
+            louds_sorted_fun,
+            louds_wavelet_fun,
+            bp_fun,
+            bp_sorted_fun,
+        ],
+        0,
+    );
+}
+
 criterion_group!(
     benches,
     create_bench_idtree,
@@ -106,6 +231,9 @@ criterion_group!(
     compare_from_id_tree,
     compare_is_leaf,
     compare_first_child,
-    compare_next_sibling
+    compare_next_sibling,
+    compare_min_max_scans,
+    bench_min_max_block_ops,
+    compare_labeled_child
 );
 criterion_main!(benches);
@@ -25,39 +25,61 @@
 //! # }
 //! ```
 
-use bincode::{deserialize, serialize};
-use bio::data_structures::rank_select::RankSelect;
 use bv::BitVec;
-use bv::Bits;
+use common::container::{Container, ContainerWriter, TreeKind};
 use common::errors::EmptyTreeError;
+use common::errors::FileError;
 use common::errors::InvalidBitvecError;
 use common::errors::NodeError;
-use common::min_max::MinMax;
-use common::succinct_tree::SuccinctTree;
-use failure::{Error, ResultExt};
+use common::label_store::LabelStore;
+use common::min_max::{BlockSize, MinMax};
+use common::parentheses::{read_bits, InputFormat};
+use common::rank_select::{PatternRankSelect, RankSelect};
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::succinct_tree::{
+    children_sorted_by_label, search_sorted_children, sort_children_by_label, LabelOrder, NodeRank,
+    SuccinctTree,
+};
 use id_tree::Node;
 use id_tree::NodeId;
 use id_tree::Tree;
+use louds_tree::LOUDSTree;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::io::Read;
+use std::mem;
+use std::sync::OnceLock;
 
-pub struct BPTree<L: PartialEq + Clone + Debug> {
-    labels: Vec<L>,
+/// A tree in balanced parentheses. The labels of the nodes are kept in pre-order in a
+/// `LabelStore`, by default a plain `Vec<L>`.
+pub struct BPTree<L: PartialEq + Clone + Debug, S: LabelStore<L> = Vec<L>> {
+    labels: S,
     rankselect: RankSelect,
     minmax: MinMax,
+    /// Counts the leaves, i.e. the occurrences of `10`, before every superblock of the bits.
+    /// Built on first use by `leaf_rank` and `leaf_select`.
+    leaves: OnceLock<PatternRankSelect>,
+    /// Set if the children of every node are sorted by their labels
+    label_order: Option<LabelOrder<L>>,
 }
 
-impl<L: PartialEq + Clone + Debug> PartialEq for BPTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> PartialEq for BPTree<L, S> {
     fn eq(&self, other: &Self) -> bool {
         self.rankselect.bits() == other.rankselect.bits()
     }
 }
 
-impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> SuccinctTree<BPTree<L, S>, L>
+    for BPTree<L, S>
+{
+    /// Returns the index of the root node.
+    fn root(&self) -> u64 {
+        0
+    }
+
     /// Checks if a node is a leaf.
     /// # Arguments
     /// * `index` The index of the node to check
@@ -65,7 +87,7 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
     /// * `NotANodeError` If `index` does not reference a node.
     fn is_leaf(&self, index: u64) -> Result<bool, NodeError> {
         self.is_valid_index(index)?;
-        Ok(!self.rankselect.bits().get_bit(index + 1))
+        Ok(!self.rankselect.get(index + 1))
     }
 
     /// Returns the index of the parent of this node
@@ -79,7 +101,7 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
         if index == 0 {
             Err(NodeError::HasNoParentError)
         } else {
-            Ok(self.minmax.enclose(index)? as u64)
+            self.minmax.enclose(index)
         }
     }
 
@@ -104,10 +126,13 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
     /// * `NotANodeError` If `index` does not reference a node.
     /// * `NoSiblingError` If `index` has no further siblings.
     fn next_sibling(&self, index: u64) -> Result<u64, NodeError> {
-        let parent_a = self.parent(index)?;
+        self.is_valid_index(index)?;
+        if index == 0 {
+            return Err(NodeError::HasNoParentError);
+        }
+        // the next sibling starts right after the node is closed, otherwise the parent ends there
         let sibling = self.minmax.find_close(index)? + 1;
-        let parent_b = self.parent(sibling)?;
-        if parent_a == parent_b {
+        if sibling < self.rankselect.len() && self.rankselect.get(sibling) {
             Ok(sibling)
         } else {
             Err(NodeError::NoSiblingError)
@@ -120,23 +145,7 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
     /// # Errors
     /// * `EmptyTreeError` If `tree` does not contain any nodes.
     fn from_id_tree(tree: Tree<L>) -> Result<Self, EmptyTreeError> {
-        let mut labels: Vec<L> = Vec::new();
-        let bitvec = if tree.height() > 0 {
-            let root_id: &NodeId = tree.root_node_id().unwrap();
-            for node in tree.traverse_pre_order(root_id).unwrap() {
-                labels.push(node.data().clone());
-            }
-            Self::traverse_id_tree_for_bitvec(tree.get(root_id).unwrap(), &tree)
-        } else {
-            return Err(EmptyTreeError);
-        };
-
-        let superblock_size = Self::calc_superblock_size(bitvec.len());
-        Ok(Self {
-            rankselect: RankSelect::new(bitvec.clone(), superblock_size as usize),
-            minmax: MinMax::new(bitvec.clone(), 1024),
-            labels,
-        })
+        BPTreeBuilder::new().build_from_id_tree(tree)
     }
 
     /// Returns the label for the edge between the parent and the node
@@ -148,11 +157,13 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
     fn child_label(&self, index: u64) -> Result<&L, NodeError> {
         self.is_valid_index(index)?;
         self.labels
-            .get((self.pre_rank(index).unwrap() - 1) as usize)
+            .get(self.pre_rank(index).unwrap() - 1)
             .ok_or(NodeError::NoLabelError)
     }
 
-    /// Returns the child from the specified node with that label
+    /// Returns the first child from the specified node with that label. If the children are
+    /// sorted by their labels, they are binary searched using `child`, otherwise the siblings
+    /// are visited from the first child on.
     /// # Arguments
     /// * `index` The index of the node to analyze
     /// * `label` The label which a should have
@@ -160,47 +171,94 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<BPTree<L>, L> for BPTree<L> {
     /// * `NotANodeError` If `index` does not reference a node.
     /// * `NoSuchChildError` If there is no child which has this label
     fn labeled_child(&self, index: u64, label: L) -> Result<u64, NodeError> {
-        self.is_valid_index(index)?;
-        let first_child = self.first_child(index)?;
-        if *self.child_label(first_child)? == label {
-            return Ok(first_child);
+        let child_count = self.degree(index)?;
+        if child_count == 0 {
+            return Err(NodeError::NoSuchChildError);
+        }
+        if let Some(order) = self.label_order {
+            let close = self.minmax.find_close(index)?;
+            return match search_sorted_children(child_count, order, &label, |n| {
+                self.child_label(self.nth_child(index, close, n)?)
+            })? {
+                Some(n) => self.nth_child(index, close, n),
+                None => Err(NodeError::NoSuchChildError),
+            };
         }
-        let mut sibling = first_child;
-        while self.next_sibling(first_child).err().is_none() {
-            sibling = self.next_sibling(sibling)?;
+        let mut child = Some(index + 1);
+        while let Some(sibling) = child {
             if *self.child_label(sibling)? == label {
                 return Ok(sibling);
             }
+            child = self.next_sibling(sibling).ok();
         }
         Err(NodeError::NoSuchChildError)
     }
+
+    /// Returns the pre-order rank of the node, starting with 0 for the root.
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn node_to_rank(&self, index: u64) -> Result<NodeRank, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(NodeRank(self.pre_rank(index).unwrap() - 1))
+    }
+
+    /// Returns the index of the node with the given pre-order rank.
+    /// # Arguments
+    /// * `rank` The rank of the node to get the index of.
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no more than `rank` nodes.
+    fn rank_to_node(&self, rank: NodeRank) -> Result<u64, NodeError> {
+        self.pre_select(rank.0 + 1)
+            .ok_or(NodeError::NoSuchNodeError)
+    }
 }
 
-impl<L: PartialEq + Clone + Debug> Debug for BPTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> SpaceUsage for BPTree<L, S> {
+    /// The bits are shared by the rank/select and the MinMax structure and counted once. The
+    /// leaf directory is counted as part of the rank directory once it has been built.
+    fn space_usage(&self) -> SpaceBreakdown {
+        let leaves = self
+            .leaves
+            .get()
+            .map_or(0, |leaves| leaves.space_usage().rank_directory);
+        let mut usage = self.rankselect.space_usage();
+        usage.rank_directory += leaves;
+        usage.minmax_heap = self.minmax.space_usage().minmax_heap;
+        usage.labels = self.labels.space_usage().total();
+        usage
+    }
+}
+
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> Debug for BPTree<L, S> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "BPTree\n  {{ bits: {:?} }}", self.rankselect.bits())
     }
 }
 
-impl<L: PartialEq + Clone + Debug> BPTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> BPTree<L, S> {
     /// Returns whether the index is valid
     /// # Arguments
     /// * `index` The index which should be valid
     /// # Errors
     /// * `NotANodeError` If `index` does not reference a node.
     pub fn is_valid_index(&self, index: u64) -> Result<bool, NodeError> {
-        if index >= self.rankselect.bits().len() {
+        // a node is referenced by its opening parenthesis
+        if index >= self.rankselect.len() || !self.rankselect.get(index) {
             Err(NodeError::NotANodeError)
         } else {
             Ok(true)
         }
     }
 
-    /// Returns the rank of this index
+    /// Returns the pre-order rank of the node, starting with 1 for the root, or `None` if
+    /// `index` does not reference a node.
     /// # Arguments
     /// * `index` The index of the node to get the rank of.
     ///
     pub fn pre_rank(&self, index: u64) -> Option<u64> {
+        self.is_valid_index(index).ok()?;
         self.rankselect.rank_1(index)
     }
 
@@ -244,74 +302,580 @@ impl<L: PartialEq + Clone + Debug> BPTree<L> {
         Ok((self.minmax.find_close(index)? - index + 1) / 2)
     }
 
+    /// Returns the index of the previous sibling
+    /// # Arguments
+    /// * `index` The index of the node to get the previous sibling of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `HasNoParentError` If `index` references the root node.
+    /// * `NoSiblingError` If `index` is the first child of its parent.
+    pub fn prev_sibling(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        if index == 0 {
+            return Err(NodeError::HasNoParentError);
+        }
+        // the previous sibling ends right before the node, otherwise the parent starts there
+        if self.rankselect.get(index - 1) {
+            Err(NodeError::NoSiblingError)
+        } else {
+            self.minmax.find_open(index - 1)
+        }
+    }
+
+    /// Returns the index of the nodes last child.
+    /// # Arguments
+    /// * `index` The index of the node to get the last child of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NotAParentError` If `index` references a leaf.
+    pub fn last_child(&self, index: u64) -> Result<u64, NodeError> {
+        if self.is_leaf(index)? {
+            Err(NodeError::NotAParentError)
+        } else {
+            self.minmax.find_open(self.minmax.find_close(index)? - 1)
+        }
+    }
+
+    /// Returns the `n`-th child of the node, starting with 1 for the first child
+    /// # Arguments
+    /// * `index` The index of the node to get the child of.
+    /// * `n` The rank of the child
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchChildError` If the node has less than `n` children.
+    pub fn child(&self, index: u64, n: u64) -> Result<u64, NodeError> {
+        if n == 0 || n > self.degree(index)? {
+            return Err(NodeError::NoSuchChildError);
+        }
+        self.nth_child(index, self.minmax.find_close(index)?, n)
+    }
+
+    /// Returns the `n`-th child of the node with the closing parenthesis at `close`. The node
+    /// must have at least `n` children.
+    fn nth_child(&self, index: u64, close: u64, n: u64) -> Result<u64, NodeError> {
+        if n == 1 {
+            return Ok(index + 1);
+        }
+        // the children end at the minima of the excess inside of the node
+        Ok(self.minmax.min_select(index + 1, close - 1, n - 1)? + 1)
+    }
+
+    /// Returns the number of children of the node
+    /// # Arguments
+    /// * `index` The index of the node to get the degree of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn degree(&self, index: u64) -> Result<u64, NodeError> {
+        if self.is_leaf(index)? {
+            return Ok(0);
+        }
+        let close = self.minmax.find_close(index)?;
+        self.minmax.min_count(index + 1, close - 1)
+    }
+
+    /// Returns the rank of the node among its siblings, starting with 0 for the first child
+    /// and the root, or `None` if `index` does not reference a node. Like
+    /// `LOUDSTree::child_rank`, the `n`-th child in `child` has the rank `n - 1`.
+    pub fn child_rank(&self, index: u64) -> Option<u64> {
+        self.is_valid_index(index).ok()?;
+        if index == self.root() {
+            return Some(0);
+        }
+        let parent = self.parent(index).ok()?;
+        if index == parent + 1 {
+            Some(0)
+        } else {
+            self.minmax.min_count(parent + 1, index - 1).ok()
+        }
+    }
+
+    /// Returns the ancestor of the node `distance` levels above it, the node itself for 0
+    /// # Arguments
+    /// * `index` The index of the node to get the ancestor of.
+    /// * `distance` The difference of the depths of the node and the ancestor
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `HasNoParentError` If the node has less than `distance` ancestors.
+    pub fn level_ancestor(&self, index: u64, distance: u64) -> Result<u64, NodeError> {
+        if distance >= self.depth(index)? {
+            return Err(NodeError::HasNoParentError);
+        }
+        // the ancestor starts right after the last position with one less excess
+        Ok(self
+            .minmax
+            .bwd_search(index, -(distance as i64) - 1)
+            .map_or(0, |position| position + 1))
+    }
+
+    /// Returns the lowest common ancestor of two nodes
+    /// # Arguments
+    /// * `x` The index of the first node
+    /// * `y` The index of the second node
+    /// # Errors
+    /// * `NotANodeError` If `x` or `y` does not reference a node.
+    pub fn lca(&self, x: u64, y: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(x)?;
+        self.is_valid_index(y)?;
+        self.minmax.double_enclose(x, y)
+    }
+
+    /// Returns the post-order rank of the node, starting with 1
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn post_rank(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(self
+            .rankselect
+            .rank_0(self.minmax.find_close(index)?)
+            .unwrap())
+    }
+
+    /// Returns the index of the node with this post-order rank
+    /// # Arguments
+    /// * `rank` The rank of the node to get the index of.
+    ///
+    pub fn post_select(&self, rank: u64) -> Option<u64> {
+        self.minmax.find_open(self.rankselect.select_0(rank)?).ok()
+    }
+
+    /// Returns the number of leaves up to the node in pre-order, including the node
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn leaf_rank(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(self.leaves().rank(&self.rankselect, index).unwrap())
+    }
+
+    /// Returns the index of the leaf with this rank among the leaves in pre-order
+    /// # Arguments
+    /// * `rank` The rank of the leaf to get the index of.
+    ///
+    pub fn leaf_select(&self, rank: u64) -> Option<u64> {
+        self.leaves().select(&self.rankselect, rank)
+    }
+
+    /// Returns the leaf directory. A leaf is a `1` followed by a `0`, so the directory is
+    /// built from the shared bits without copying them.
+    fn leaves(&self) -> &PatternRankSelect {
+        self.leaves
+            .get_or_init(|| PatternRankSelect::new(&self.rankselect))
+    }
+
+    /// Returns the height of the subtree of the node, 0 for a leaf
+    /// # Arguments
+    /// * `index` The index of the node to get the height of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn height(&self, index: u64) -> Result<u64, NodeError> {
+        let deepest = self.deepest_node(index)?;
+        Ok(self.depth(deepest)? - self.depth(index)?)
+    }
+
+    /// Returns the first node in pre-order with the maximal depth in the subtree of the node
+    /// # Arguments
+    /// * `index` The index of the node to search the subtree of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn deepest_node(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        let close = self.minmax.find_close(index)?;
+        self.minmax.rMq(index, close)
+    }
+
+    /// Returns the next node to the right with the same depth
+    /// # Arguments
+    /// * `index` The index of the node to get the neighbour of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchNodeError` If there is no node to the right with the same depth.
+    pub fn level_next(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        let close = self.minmax.find_close(index)?;
+        self.minmax
+            .fwd_search(close, 1)
+            .ok_or(NodeError::NoSuchNodeError)
+    }
+
+    /// Returns the next node to the left with the same depth
+    /// # Arguments
+    /// * `index` The index of the node to get the neighbour of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchNodeError` If there is no node to the left with the same depth.
+    pub fn level_prev(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        // the last position with the same excess is right before the neighbour is closed
+        match self.minmax.bwd_search(index, 0) {
+            Some(position) => self.minmax.find_open(position + 1),
+            None => Err(NodeError::NoSuchNodeError),
+        }
+    }
+
     /// Returns a BPTree from a given BitVec
     /// # Arguments
     /// * `bitvec` The BitVec for the specified BPTree
     ///
     pub fn from_bitvec(bitvec: BitVec<u8>) -> Result<Self, InvalidBitvecError> {
-        if !Self::is_valid(&bitvec as &BitVec<u8>) {
-            return Err(InvalidBitvecError);
+        BPTreeBuilder::new().build_from_bitvec(bitvec)
+    }
+
+    /// Reads a BPTree from a parenthesis sequence, e.g. a file. The sequence is validated
+    /// while it is read.
+    /// # Arguments
+    /// * `reader` The input containing the sequence
+    /// * `format` Whether the input is text of `(` and `)` or packed bits
+    /// # Errors
+    /// * Every parse `FileError` with the byte offset of the first invalid input, see `read_bits`.
+    pub fn from_reader<R: Read>(reader: R, format: InputFormat) -> Result<Self, FileError> {
+        BPTreeBuilder::new().build_from_reader(reader, format)
+    }
+
+    /// Creates a BPTree from a valid bit vector and its labels in pre-order.
+    pub(crate) fn from_parts(bitvec: BitVec<u8>, labels: Vec<L>) -> Self {
+        BPTreeBuilder::new().build(bitvec, labels)
+    }
+
+    /// Returns the MinMax structure of the tree.
+    pub fn minmax(&self) -> &MinMax {
+        &self.minmax
+    }
+
+    /// Returns the number of bytes used by the tree, including storage that is mapped from a
+    /// file. The bits are stored once and shared by the rank/select and the MinMax structure,
+    /// so without labels the tree needs 2n bits plus its directories for n nodes. Labels are
+    /// counted as reported by their `LabelStore`.
+    pub fn heap_size_bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.space_usage().total()
+    }
+
+    /// Converts the tree into a LOUDSTree with the same shape and labels. The nodes are
+    /// visited in level order directly on the bit vector, so apart from the new tree only
+    /// a constant number of words is needed.
+    pub fn to_louds(&self) -> LOUDSTree<L, S> {
+        if self.rankselect.is_empty() {
+            return LOUDSTree::from_parts(BitVec::new(), Vec::new())
+                .with_label_order(self.label_order);
         }
-        let superblock_size = Self::calc_superblock_size(bitvec.len());
-        Ok(Self {
-            minmax: MinMax::new(bitvec.clone(), 1024),
-            labels: Vec::with_capacity(bitvec.len() as usize),
-            rankselect: RankSelect::new(bitvec, superblock_size as usize),
-        })
+        let mut bitvec = BitVec::with_capacity(self.rankselect.len());
+        bitvec.push(true);
+        let mut labels = Vec::with_capacity(self.labels.len() as usize);
+        let mut level_start = Some(0);
+        while let Some(first) = level_start {
+            // the first node of the next level is the first child of the first
+            // node with children on this level
+            level_start = None;
+            let mut node = Some(first);
+            while let Some(index) = node {
+                if let Some(label) = self.labels.get(self.pre_rank(index).unwrap() - 1) {
+                    labels.push(label.clone());
+                }
+                let mut child = self.first_child(index).ok();
+                if level_start.is_none() {
+                    level_start = child;
+                }
+                while let Some(index) = child {
+                    bitvec.push(true);
+                    child = self.next_sibling(index).ok();
+                }
+                bitvec.push(false);
+                node = self.level_next(index).ok();
+            }
+        }
+        LOUDSTree::from_parts(bitvec, labels).with_label_order(self.label_order)
+    }
+
+    /// Returns whether the children of every node are sorted by their labels, so that
+    /// `labeled_child` uses binary search.
+    pub fn has_sorted_children(&self) -> bool {
+        self.label_order.is_some()
+    }
+
+    pub(crate) fn with_label_order(mut self, label_order: Option<LabelOrder<L>>) -> Self {
+        self.label_order = label_order;
+        self
     }
 
-    /// Deserializes a BPTree from a given file
+    fn traverse_id_tree_for_bitvec(node: &Node<L>, tree: &Tree<L>, bitvec: &mut BitVec<u8>) {
+        bitvec.push(true);
+        for child in node.children() {
+            Self::traverse_id_tree_for_bitvec(tree.get(child).unwrap(), tree, bitvec);
+        }
+        bitvec.push(false);
+    }
+}
+
+impl<L: Ord + Clone + Debug, S: LabelStore<L>> BPTree<L, S> {
+    /// Constructs a BPTree from a IDTree with the children of every node sorted by their
+    /// labels, so `labeled_child` binary searches the children instead of visiting them one
+    /// by one. Children with equal labels keep their order.
+    /// # Arguments
+    /// * `tree` The IDTree which should be converted
+    /// # Errors
+    /// * `EmptyTreeError` If `tree` does not contain any nodes.
+    pub fn from_id_tree_sorted(tree: Tree<L>) -> Result<Self, EmptyTreeError> {
+        BPTreeBuilder::new().build_from_id_tree_sorted(tree)
+    }
+
+    /// Lets `labeled_child` binary search the children if the children of every node are
+    /// sorted by their labels, e.g. after loading a tree built by `from_id_tree_sorted`.
+    /// Returns whether they are sorted, which takes time linear in the number of nodes.
+    pub fn enable_sorted_children(&mut self) -> bool {
+        if children_sorted_by_label(self).unwrap_or(false) {
+            self.label_order = Some(L::cmp);
+        }
+        self.has_sorted_children()
+    }
+}
+
+impl<L, S> BPTree<L, S>
+where
+    L: PartialEq + Clone + Debug + Serialize + DeserializeOwned,
+    S: LabelStore<L>,
+{
+    /// Deserializes a BPTree from a given file. Labels and, if the file contains it,
+    /// the precomputed MinMax heap are restored as well.
     /// # Arguments
     /// * `path` The path of the file to deserialize
-    ///
-    pub fn from_file(path: String) -> Result<Self, Error> {
-        let file = fs::read(path).context("Could not read saved tree.")?;
-        let rankselect: RankSelect = deserialize(&file).context("Error while deserializing tree.")?;
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a BPTree.
+    /// * `InvalidBitvecError` If the stored bits are not a valid BPTree.
+    /// * `LabelCountError` If the number of labels does not match the number of nodes.
+    /// * Every other `FileError` if the file cannot be read or is corrupt.
+    pub fn from_file(path: String) -> Result<Self, FileError> {
+        BPTreeBuilder::new().build_from_file(path)
+    }
+
+    /// Opens a BPTree read-only from a memory-mapped file written by `save_to` or
+    /// `save_to_with_minmax`. The bits, the rank/select directory and the MinMax heap are used
+    /// directly from the file and loaded lazily on first access, so opening a file saved with
+    /// `save_to_with_minmax` takes constant time apart from decoding the labels. Without a
+    /// stored heap, the heap is built in memory. Neither the checksum nor the validity of the
+    /// bits is checked.
+    /// # Arguments
+    /// * `path` The path of the file to map
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a BPTree.
+    /// * Every other `FileError` if the file cannot be mapped or its header is corrupt.
+    /// # Safety
+    /// The file must not be modified or truncated while the tree is alive.
+    pub unsafe fn open_mapped(path: String) -> Result<Self, FileError> {
+        Self::from_container(
+            &Container::open_mapped(&path, TreeKind::BP)?,
+            BlockSize::default(),
+        )
+    }
+
+    fn from_container(container: &Container, block_size: BlockSize) -> Result<Self, FileError> {
+        let rankselect = container.rank_select()?;
+        let minmax = match container.minmax(&rankselect)? {
+            Some(minmax) => minmax,
+            None => MinMax::from_words(
+                rankselect.len(),
+                rankselect.words().clone(),
+                block_size.bits_for(rankselect.len()),
+            ),
+        };
         Ok(Self {
-            minmax: MinMax::new(rankselect.bits().clone(), 1024),
-            labels: Vec::with_capacity(rankselect.bits().len() as usize),
+            labels: S::from_labels(container.labels()?),
             rankselect,
+            minmax,
+            leaves: OnceLock::new(),
+            label_order: None,
         })
     }
 
-    /// Serializes a BPTree to a file
+    /// Serializes a BPTree including its labels to a file. The MinMax heap is not saved and
+    /// will be rebuilt when loading the tree.
     /// # Arguments
     /// * `path` The path of the file to save to. Will be overwritten if it exists.
     ///
-    pub fn save_to(&self, path: String) -> Result<(), Error> {
-        let encoded = serialize(&self.rankselect).context("Error while serializing tree.")?;
-        let mut file = File::create(path).context("Could not save tree.")?;
-        file.write_all(&encoded)?;
-        Ok(())
+    pub fn save_to(&self, path: String) -> Result<(), FileError> {
+        self.write_to_file(&path, false)
     }
 
-    fn traverse_id_tree_for_bitvec(node: &Node<L>, tree: &Tree<L>) -> BitVec<u8> {
-        let mut bitvec = BitVec::new();
-        bitvec.push(true);
-        for child in node.children() {
-            let bitvec_rec = Self::traverse_id_tree_for_bitvec(tree.get(child).unwrap(), &tree);
-            for bit in 0..bitvec_rec.len() {
-                bitvec.push(bitvec_rec.get_bit(bit));
+    /// Serializes a BPTree including its labels and the precomputed MinMax heap to a file.
+    /// This results in a bigger file, but avoids rebuilding the heap in `from_file`.
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    ///
+    pub fn save_to_with_minmax(&self, path: String) -> Result<(), FileError> {
+        self.write_to_file(&path, true)
+    }
+
+    fn write_to_file(&self, path: &str, with_minmax: bool) -> Result<(), FileError> {
+        let len = self.rankselect.len();
+        let mut writer = ContainerWriter::new(TreeKind::BP, len / 2, len);
+        writer.add_rank_select(&self.rankselect);
+        writer.add_labels(&self.labels.to_vec())?;
+        if with_minmax {
+            writer.add_minmax(&self.minmax);
+        }
+        writer.write_to(path)
+    }
+}
+
+/// Creates BPTrees with a chosen block size of their MinMax structure. The constructors of
+/// `BPTree` use `BlockSize::default()`.
+///
+/// Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate bv;
+/// # extern crate fp_succinct_trees_1;
+///
+/// # fn main() {
+/// use fp_succinct_trees_1::bp_tree::{BPTree, BPTreeBuilder};
+///
+/// let bitvec = bit_vec![true, true, false, true, false, false];
+/// let tree: BPTree<i32> = BPTreeBuilder::new()
+///     .block_size(256)
+///     .build_from_bitvec(bitvec)
+///     .unwrap();
+/// assert_eq!(tree.minmax().block_size(), 256);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BPTreeBuilder {
+    block_size: BlockSize,
+}
+
+impl BPTreeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses blocks of `block_size` bits.
+    /// # Panics
+    /// If `block_size` is 0.
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        assert!(block_size > 0, "The block size must not be 0");
+        self.block_size = BlockSize::Fixed(block_size);
+        self
+    }
+
+    /// Chooses the block size from the length of each tree, see `BlockSize::Auto`.
+    pub fn auto_block_size(mut self) -> Self {
+        self.block_size = BlockSize::Auto;
+        self
+    }
+
+    /// Like `BPTree::from_bitvec`, with the configured block size.
+    pub fn build_from_bitvec<L: PartialEq + Clone + Debug, S: LabelStore<L>>(
+        &self,
+        bitvec: BitVec<u8>,
+    ) -> Result<BPTree<L, S>, InvalidBitvecError> {
+        if let Some(position) = BPTree::<L, S>::invalid_position(&bitvec as &BitVec<u8>) {
+            return Err(InvalidBitvecError { position });
+        }
+        Ok(self.build(bitvec, Vec::new()))
+    }
+
+    /// Like `BPTree::from_id_tree`, with the configured block size.
+    pub fn build_from_id_tree<L: PartialEq + Clone + Debug, S: LabelStore<L>>(
+        &self,
+        tree: Tree<L>,
+    ) -> Result<BPTree<L, S>, EmptyTreeError> {
+        let mut labels: Vec<L> = Vec::new();
+        let bitvec = if tree.height() > 0 {
+            let root_id: &NodeId = tree.root_node_id().unwrap();
+            for node in tree.traverse_pre_order(root_id).unwrap() {
+                labels.push(node.data().clone());
             }
+            let mut bitvec = BitVec::with_capacity(2 * labels.len() as u64);
+            BPTree::<L, S>::traverse_id_tree_for_bitvec(
+                tree.get(root_id).unwrap(),
+                &tree,
+                &mut bitvec,
+            );
+            bitvec
+        } else {
+            return Err(EmptyTreeError);
+        };
+
+        Ok(self.build(bitvec, labels))
+    }
+
+    /// Like `BPTree::from_id_tree_sorted`, with the configured block size.
+    pub fn build_from_id_tree_sorted<L: Ord + Clone + Debug, S: LabelStore<L>>(
+        &self,
+        mut tree: Tree<L>,
+    ) -> Result<BPTree<L, S>, EmptyTreeError> {
+        sort_children_by_label(&mut tree);
+        Ok(self
+            .build_from_id_tree(tree)?
+            .with_label_order(Some(L::cmp)))
+    }
+
+    /// Like `BPTree::from_reader`, with the configured block size.
+    pub fn build_from_reader<L: PartialEq + Clone + Debug, S: LabelStore<L>, R: Read>(
+        &self,
+        reader: R,
+        format: InputFormat,
+    ) -> Result<BPTree<L, S>, FileError> {
+        Ok(self.build(read_bits(reader, format)?, Vec::new()))
+    }
+
+    /// Like `BPTree::from_file`. The configured block size is only used if the file does not
+    /// contain a MinMax heap, a stored heap keeps its block size.
+    pub fn build_from_file<L, S>(&self, path: String) -> Result<BPTree<L, S>, FileError>
+    where
+        L: PartialEq + Clone + Debug + Serialize + DeserializeOwned,
+        S: LabelStore<L>,
+    {
+        let container = Container::from_file(&path, TreeKind::BP)?;
+        let tree = BPTree::<L, S>::from_container(&container, self.block_size)?;
+        if let Some(position) = BPTree::<L, S>::invalid_position(&tree.rankselect.bits()) {
+            return Err(FileError::InvalidBitvecError { position });
+        }
+        Ok(tree)
+    }
+
+    fn build<L: PartialEq + Clone + Debug, S: LabelStore<L>>(
+        &self,
+        bitvec: BitVec<u8>,
+        labels: Vec<L>,
+    ) -> BPTree<L, S> {
+        // the MinMax structure shares the words of the rank/select structure
+        let rankselect = RankSelect::new(&bitvec);
+        let block_size = self.block_size.bits_for(rankselect.len());
+        let minmax = MinMax::from_words(rankselect.len(), rankselect.words().clone(), block_size);
+        BPTree {
+            rankselect,
+            labels: S::from_labels(labels),
+            minmax,
+            leaves: OnceLock::new(),
+            label_order: None,
         }
-        bitvec.push(false);
-        bitvec
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::label_store::DictionaryLabels;
+    use common::min_max::MinMaxNode;
+    use common::test_util::{
+        arb_id_tree, check_node_indices, linear_labeled_child, pre_order_shape,
+        with_small_alphabet, IndexCheck,
+    };
     use id_tree::InsertBehavior::AsRoot;
     use id_tree::InsertBehavior::UnderNode;
     use id_tree::TreeBuilder;
+    use louds_tree::LOUDSTree;
+    use std::collections::HashMap;
+    use std::fs;
 
     #[test]
     fn new_from_bitvec() {
         let bitvec = bit_vec!(true, false);
         let tree: BPTree<String> = BPTree::from_bitvec(bitvec.clone()).unwrap();
         assert_eq!(
-            tree.rankselect.bits().clone(),
+            tree.rankselect.to_bitvec(),
             bitvec,
             "BPTree seems to somehow change the bitvector it was created with."
         );
@@ -321,7 +885,36 @@ mod tests {
     fn new_from_bitvec_invalid() {
         let bitvec = bit_vec!(false, false);
         let tree: Result<BPTree<String>, InvalidBitvecError> = BPTree::from_bitvec(bitvec.clone());
-        assert_eq!(tree.unwrap_err(), InvalidBitvecError);
+        assert_eq!(tree.unwrap_err(), InvalidBitvecError { position: 0 });
+    }
+
+    #[test]
+    fn new_from_bitvec_negative_excess() {
+        let bitvec = bit_vec!(false, true);
+        let tree: Result<BPTree<String>, InvalidBitvecError> = BPTree::from_bitvec(bitvec.clone());
+        assert_eq!(tree.unwrap_err(), InvalidBitvecError { position: 0 });
+    }
+
+    #[test]
+    fn from_reader() {
+        let tree: BPTree<String> =
+            BPTree::from_reader("(()(()))".as_bytes(), InputFormat::Parentheses).unwrap();
+        let expected =
+            BPTree::from_bitvec(bit_vec![true, true, false, true, true, false, false, false])
+                .unwrap();
+        assert_eq!(tree, expected);
+        assert_eq!(tree.subtree_size(0).unwrap(), 4);
+        let tree: BPTree<String> =
+            BPTree::from_reader(&[0b0001_1011][..], InputFormat::PackedBits).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn from_reader_invalid() {
+        match BPTree::<String>::from_reader("(()))".as_bytes(), InputFormat::Parentheses) {
+            Err(FileError::TrailingDataError(4)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
     }
 
     #[test]
@@ -338,10 +931,249 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error while deserializing tree.")]
+    fn save_load_labels() {
+        let mut id_tree: Tree<String> = TreeBuilder::new().with_node_capacity(4).build();
+        let root_id: NodeId = id_tree
+            .insert(Node::new(String::from("root")), AsRoot)
+            .unwrap();
+        let child_id = id_tree
+            .insert(Node::new(String::from("child")), UnderNode(&root_id))
+            .unwrap();
+        id_tree
+            .insert(Node::new(String::from("leaf")), UnderNode(&child_id))
+            .unwrap();
+        let tree = BPTree::from_id_tree(id_tree).unwrap();
+        let path = "testdata/bptree_labels.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: BPTree<String> = BPTree::from_file(path.to_string()).unwrap();
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        assert_eq!(*result.child_label(2).unwrap(), "leaf");
+    }
+
+    #[test]
+    fn save_load_label_store() {
+        let bitvec = bit_vec![true, true, false, true, false, true, false, false];
+        let tree: BPTree<String> = BPTree::from_parts(
+            bitvec,
+            vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string(),
+                "a".to_string(),
+            ],
+        );
+        let path = "testdata/bptree_label_store.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: BPTree<String, DictionaryLabels<String>> =
+            BPTree::from_file(path.to_string()).unwrap();
+        assert_eq!(result.labels.to_vec(), tree.labels);
+        assert_eq!(*result.child_label(5).unwrap(), "a");
+        assert_eq!(result.labeled_child(0, "a".to_string()).unwrap(), 3);
+        assert!(result.space_usage().labels < tree.space_usage().labels);
+        assert_eq!(result.to_louds().to_bp(), result);
+    }
+
+    #[test]
+    fn save_load_with_minmax() {
+        let mut id_tree: Tree<i32> = TreeBuilder::new().with_node_capacity(4).build();
+        let root_id: NodeId = id_tree.insert(Node::new(0), AsRoot).unwrap();
+        id_tree.insert(Node::new(1), UnderNode(&root_id)).unwrap();
+        id_tree.insert(Node::new(2), UnderNode(&root_id)).unwrap();
+        let tree = BPTree::from_id_tree(id_tree).unwrap();
+        let path = "testdata/bptree_minmax.testdata";
+        tree.save_to_with_minmax(path.to_string()).unwrap();
+        let result: BPTree<i32> = BPTree::from_file(path.to_string()).unwrap();
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        assert_eq!(result.next_sibling(1).unwrap(), 3);
+    }
+
+    #[test]
+    fn builder_block_size() {
+        let bitvec = bit_vec![true, true, false, true, true, false, false, false];
+        let tree: BPTree<i32> = BPTree::from_bitvec(bitvec.clone()).unwrap();
+        assert_eq!(tree.minmax().block_size(), 1024);
+        let small: BPTree<i32> = BPTreeBuilder::new()
+            .block_size(2)
+            .build_from_bitvec(bitvec.clone())
+            .unwrap();
+        assert_eq!(small.minmax().block_size(), 2);
+        assert_eq!(small, tree);
+        assert_eq!(small.parent(4).unwrap(), 3);
+        assert_eq!(small.next_sibling(1).unwrap(), 3);
+        let auto: BPTree<i32> = BPTreeBuilder::new()
+            .auto_block_size()
+            .build_from_reader("(()(()))".as_bytes(), InputFormat::Parentheses)
+            .unwrap();
+        assert_eq!(auto.minmax().block_size(), 64);
+        assert!(BPTreeBuilder::new()
+            .build_from_bitvec::<i32, Vec<i32>>(bit_vec![true, true])
+            .is_err());
+    }
+
+    #[test]
+    fn shared_bits() {
+        let mut bitvec = BitVec::new();
+        for _ in 0..50_000 {
+            bitvec.push(true);
+            bitvec.push(true);
+            bitvec.push(false);
+        }
+        for _ in 0..50_000 {
+            bitvec.push(false);
+        }
+        let tree: BPTree<()> = BPTree::from_bitvec(bitvec).unwrap();
+        assert!(tree.rankselect.words().shares_with(tree.minmax.words()));
+        let bits = tree.rankselect.words().len() * 8;
+        let directories = tree.rankselect.superblocks().len() * 8
+            + tree.minmax.heap().len() * mem::size_of::<MinMaxNode>();
+        assert_eq!(
+            tree.heap_size_bytes(),
+            mem::size_of::<BPTree<()>>() + bits + directories
+        );
+        // 100000 nodes in 2n bits plus less than 2n bits for the directories
+        assert!(tree.heap_size_bytes() * 8 < 4 * 100_000);
+        // the leaf directory adds one word per superblock and no bits
+        tree.leaf_rank(1).unwrap();
+        assert_eq!(tree.space_usage().bits, bits);
+        assert_eq!(
+            tree.heap_size_bytes(),
+            mem::size_of::<BPTree<()>>()
+                + bits
+                + directories
+                + tree.rankselect.superblocks().len() * 8
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder_zero_block_size() {
+        BPTreeBuilder::new().block_size(0);
+    }
+
+    #[test]
+    fn builder_from_file() {
+        let mut id_tree: Tree<i32> = TreeBuilder::new().with_node_capacity(3).build();
+        let root_id: NodeId = id_tree.insert(Node::new(0), AsRoot).unwrap();
+        id_tree.insert(Node::new(1), UnderNode(&root_id)).unwrap();
+        id_tree.insert(Node::new(2), UnderNode(&root_id)).unwrap();
+        let tree: BPTree<_> = BPTreeBuilder::new()
+            .block_size(2)
+            .build_from_id_tree(id_tree)
+            .unwrap();
+        let path = "testdata/bptree_builder.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: BPTree<i32> = BPTreeBuilder::new()
+            .block_size(4)
+            .build_from_file(path.to_string())
+            .unwrap();
+        assert_eq!(result.minmax().block_size(), 4);
+        assert_eq!(result.next_sibling(1).unwrap(), 3);
+        tree.save_to_with_minmax(path.to_string()).unwrap();
+        let result: BPTree<i32> = BPTreeBuilder::new()
+            .block_size(4)
+            .build_from_file(path.to_string())
+            .unwrap();
+        assert_eq!(result.minmax().block_size(), 2);
+        assert_eq!(result.labels, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn open_mapped() {
+        let mut id_tree: Tree<i32> = TreeBuilder::new().with_node_capacity(4).build();
+        let root_id: NodeId = id_tree.insert(Node::new(0), AsRoot).unwrap();
+        let child_id = id_tree.insert(Node::new(1), UnderNode(&root_id)).unwrap();
+        id_tree.insert(Node::new(2), UnderNode(&child_id)).unwrap();
+        id_tree.insert(Node::new(3), UnderNode(&root_id)).unwrap();
+        let tree = BPTree::from_id_tree(id_tree).unwrap();
+        let path = "testdata/bptree_mapped.testdata";
+        tree.save_to_with_minmax(path.to_string()).unwrap();
+        let result: BPTree<i32> = unsafe { BPTree::open_mapped(path.to_string()).unwrap() };
+        assert!(result.rankselect.words().is_mapped());
+        assert!(result.rankselect.superblocks().is_mapped());
+        assert!(result.minmax.heap().is_mapped());
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        for index in &[0, 1, 2, 5] {
+            assert_eq!(
+                result.first_child(*index).ok(),
+                tree.first_child(*index).ok()
+            );
+            assert_eq!(result.parent(*index).ok(), tree.parent(*index).ok());
+            assert_eq!(
+                result.next_sibling(*index).ok(),
+                tree.next_sibling(*index).ok()
+            );
+            assert_eq!(result.child_label(*index), tree.child_label(*index));
+        }
+        assert_eq!(result.first_child(1).unwrap(), 2);
+        assert_eq!(result.next_sibling(1).unwrap(), 5);
+        assert_eq!(result.subtree_size(0).unwrap(), 4);
+    }
+
+    #[test]
+    fn open_mapped_without_minmax() {
+        let tree: BPTree<String> = BPTree::from_bitvec(bit_vec![true, true, false, false]).unwrap();
+        let path = "testdata/bptree_mapped_no_minmax.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: BPTree<String> = unsafe { BPTree::open_mapped(path.to_string()).unwrap() };
+        assert!(result.rankselect.words().is_mapped());
+        assert!(!result.minmax.heap().is_mapped());
+        assert_eq!(tree, result);
+        assert_eq!(result.subtree_size(0).unwrap(), 2);
+    }
+
+    #[test]
     fn load_invalid() {
-        let _tree: BPTree<String> =
-            BPTree::from_file("testdata/bptree_invalid.testdata".to_string()).unwrap();
+        let tree: Result<BPTree<String>, FileError> =
+            BPTree::from_file("testdata/bptree_invalid.testdata".to_string());
+        match tree {
+            Err(FileError::NotATreeFileError) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_invalid_bits() {
+        let bitvec = bit_vec![true, false, true, false];
+        let mut writer = ContainerWriter::new(TreeKind::BP, 2, 4);
+        writer.add_rank_select(&RankSelect::new(&bitvec));
+        let path = "testdata/bptree_invalid_bits.testdata";
+        writer.write_to(path).unwrap();
+        match BPTree::<String>::from_file(path.to_string()) {
+            Err(FileError::InvalidBitvecError { position }) => assert_eq!(position, 1),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_louds() {
+        let louds: LOUDSTree<String> = LOUDSTree::from_bitvec(bit_vec![true, false]).unwrap();
+        let path = "testdata/bptree_louds.testdata";
+        louds.save_to(path.to_string()).unwrap();
+        match BPTree::<String>::from_file(path.to_string()) {
+            Err(FileError::WrongTreeKindError { expected, found }) => {
+                assert_eq!(expected, TreeKind::BP);
+                assert_eq!(found, TreeKind::LOUDS);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_corrupt() {
+        let tree: BPTree<String> = BPTree::from_bitvec(bit_vec![true, false]).unwrap();
+        let path = "testdata/bptree_corrupt.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let mut bytes = fs::read(path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(path, bytes).unwrap();
+        match BPTree::<String>::from_file(path.to_string()) {
+            Err(FileError::ChecksumError) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
     }
 
     #[test]
@@ -411,11 +1243,29 @@ mod tests {
     fn pre_rank() {
         let bitvec = bit_vec!(true, true, false, true, false, false);
         let tree: BPTree<String> = BPTree::from_bitvec(bitvec.clone()).unwrap();
+        assert_eq!(tree.pre_rank(0).unwrap(), 1);
         assert_eq!(tree.pre_rank(1).unwrap(), 2);
-        assert_eq!(tree.pre_rank(2).unwrap(), 2);
+        assert_eq!(tree.pre_rank(2), None);
         assert_eq!(tree.pre_rank(3).unwrap(), 3);
-        assert_eq!(tree.pre_rank(4).unwrap(), 3);
+        assert_eq!(tree.pre_rank(4), None);
         assert_eq!(tree.pre_rank(6), None);
+        let tree: BPTree<String> = BPTree::from_bitvec(bit_vec!(true, false)).unwrap();
+        assert_eq!(tree.pre_rank(1), None);
+    }
+
+    #[test]
+    fn node_to_rank() {
+        let bitvec = bit_vec!(true, true, false, true, false, false);
+        let tree: BPTree<String> = BPTree::from_bitvec(bitvec.clone()).unwrap();
+        assert_eq!(tree.node_to_rank(0).unwrap(), NodeRank(0));
+        assert_eq!(tree.node_to_rank(3).unwrap(), NodeRank(2));
+        assert_eq!(tree.rank_to_node(NodeRank(1)).unwrap(), 1);
+        assert_eq!(tree.node_to_rank(2).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(tree.node_to_rank(6).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(
+            tree.rank_to_node(NodeRank(3)).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
     }
 
     #[test]
@@ -463,6 +1313,24 @@ mod tests {
         assert_eq!(tree.subtree_size(3).unwrap(), 1);
     }
 
+    #[test]
+    fn closing_parenthesis() {
+        let bitvec = bit_vec!(true, true, false, true, false, false);
+        let tree: BPTree<String> = BPTree::from_bitvec(bitvec.clone()).unwrap();
+        for index in &[2, 4, 5] {
+            assert_eq!(tree.is_leaf(*index).unwrap_err(), NodeError::NotANodeError);
+            assert_eq!(tree.parent(*index).unwrap_err(), NodeError::NotANodeError);
+            assert_eq!(
+                tree.subtree_size(*index).unwrap_err(),
+                NodeError::NotANodeError
+            );
+            assert_eq!(
+                tree.ancestor(0, *index).unwrap_err(),
+                NodeError::NotANodeError
+            );
+        }
+    }
+
     #[test]
     fn traverse_id_tree_for_bitvec() {
         let bitvec = bit_vec!(true, true, true, false, false, true, false, false);
@@ -483,6 +1351,14 @@ mod tests {
         assert_eq!(bp_tree.unwrap_err(), EmptyTreeError);
     }
 
+    #[test]
+    fn to_louds_empty_tree() {
+        let tree: BPTree<String> = BPTree::from_bitvec(BitVec::new()).unwrap();
+        let converted = tree.to_louds();
+        assert_eq!(converted, LOUDSTree::from_bitvec(BitVec::new()).unwrap());
+        assert_eq!(converted.to_bp(), tree);
+    }
+
     #[test]
     fn print() {
         let bitvec = bit_vec!(true, true, false, true, false, false);
@@ -515,7 +1391,7 @@ mod tests {
                 UnderNode(&root_id),
             )
             .unwrap();
-        let bp_tree = BPTree::from_id_tree(id_tree).unwrap();
+        let bp_tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
         assert_eq!(*bp_tree.child_label(0).unwrap(), "root");
         assert_eq!(*bp_tree.child_label(1).unwrap(), "first_root_child");
         assert_eq!(*bp_tree.child_label(2).unwrap(), "leaf");
@@ -543,7 +1419,7 @@ mod tests {
                 UnderNode(&root_id),
             )
             .unwrap();
-        let bp_tree = BPTree::from_id_tree(id_tree).unwrap();
+        let bp_tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
         assert_eq!(
             bp_tree
                 .labeled_child(0, String::from("second_root_child"))
@@ -557,5 +1433,334 @@ mod tests {
             1
         );
         assert_eq!(bp_tree.labeled_child(1, String::from("leaf")).unwrap(), 2);
+        assert_eq!(
+            bp_tree
+                .labeled_child(0, String::from("foobar"))
+                .unwrap_err(),
+            NodeError::NoSuchChildError
+        );
+        assert_eq!(
+            bp_tree.labeled_child(2, String::from("leaf")).unwrap_err(),
+            NodeError::NoSuchChildError
+        );
+    }
+
+    /// `( ( () () ) ( () ) () )` with the inner nodes at 0, 1 and 7 and the leaves
+    /// at 2, 4, 8 and 11
+    fn navigation_tree() -> BPTree<String> {
+        BPTree::from_bitvec(bit_vec![
+            true, true, true, false, true, false, false, true, true, false, false, true, false,
+            false
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn prev_sibling() {
+        let tree = navigation_tree();
+        assert_eq!(tree.prev_sibling(7).unwrap(), 1);
+        assert_eq!(tree.prev_sibling(11).unwrap(), 7);
+        assert_eq!(tree.prev_sibling(4).unwrap(), 2);
+        assert_eq!(tree.prev_sibling(1).unwrap_err(), NodeError::NoSiblingError);
+        assert_eq!(
+            tree.prev_sibling(0).unwrap_err(),
+            NodeError::HasNoParentError
+        );
+    }
+
+    #[test]
+    fn last_child() {
+        let tree = navigation_tree();
+        assert_eq!(tree.last_child(0).unwrap(), 11);
+        assert_eq!(tree.last_child(1).unwrap(), 4);
+        assert_eq!(tree.last_child(7).unwrap(), 8);
+        assert_eq!(tree.last_child(2).unwrap_err(), NodeError::NotAParentError);
+    }
+
+    #[test]
+    fn degree_and_child() {
+        let tree = navigation_tree();
+        assert_eq!(tree.degree(0).unwrap(), 3);
+        assert_eq!(tree.degree(1).unwrap(), 2);
+        assert_eq!(tree.degree(2).unwrap(), 0);
+        assert_eq!(tree.child(0, 1).unwrap(), 1);
+        assert_eq!(tree.child(0, 2).unwrap(), 7);
+        assert_eq!(tree.child(0, 3).unwrap(), 11);
+        assert_eq!(tree.child(0, 4).unwrap_err(), NodeError::NoSuchChildError);
+        assert_eq!(tree.child(2, 1).unwrap_err(), NodeError::NoSuchChildError);
+        assert_eq!(tree.child_rank(1).unwrap(), 0);
+        assert_eq!(tree.child_rank(7).unwrap(), 1);
+        assert_eq!(tree.child_rank(11).unwrap(), 2);
+        assert_eq!(tree.child_rank(0).unwrap(), 0);
+        assert_eq!(tree.child_rank(3), None);
+    }
+
+    #[test]
+    fn level_ancestor() {
+        let tree = navigation_tree();
+        assert_eq!(tree.level_ancestor(8, 0).unwrap(), 8);
+        assert_eq!(tree.level_ancestor(8, 1).unwrap(), 7);
+        assert_eq!(tree.level_ancestor(8, 2).unwrap(), 0);
+        assert_eq!(
+            tree.level_ancestor(8, 3).unwrap_err(),
+            NodeError::HasNoParentError
+        );
+    }
+
+    #[test]
+    fn lca() {
+        let tree = navigation_tree();
+        assert_eq!(tree.lca(2, 8).unwrap(), 0);
+        assert_eq!(tree.lca(4, 2).unwrap(), 1);
+        assert_eq!(tree.lca(1, 4).unwrap(), 1);
+        assert_eq!(tree.lca(4, 4).unwrap(), 4);
+        assert_eq!(tree.lca(8, 11).unwrap(), 0);
+    }
+
+    #[test]
+    fn post_rank_and_select() {
+        let tree = navigation_tree();
+        for (rank, index) in [2, 4, 1, 8, 7, 11, 0].iter().enumerate() {
+            assert_eq!(tree.post_rank(*index).unwrap(), rank as u64 + 1);
+            assert_eq!(tree.post_select(rank as u64 + 1).unwrap(), *index);
+        }
+        assert_eq!(tree.post_select(0), None);
+        assert_eq!(tree.post_select(8), None);
+    }
+
+    #[test]
+    fn leaf_rank_and_select() {
+        let tree = navigation_tree();
+        assert_eq!(tree.leaf_rank(0).unwrap(), 0);
+        assert_eq!(tree.leaf_rank(2).unwrap(), 1);
+        assert_eq!(tree.leaf_rank(7).unwrap(), 2);
+        assert_eq!(tree.leaf_rank(11).unwrap(), 4);
+        assert_eq!(tree.leaf_select(3).unwrap(), 8);
+        assert_eq!(tree.leaf_select(5), None);
+    }
+
+    #[test]
+    fn height_and_deepest_node() {
+        let tree = navigation_tree();
+        assert_eq!(tree.height(0).unwrap(), 2);
+        assert_eq!(tree.height(7).unwrap(), 1);
+        assert_eq!(tree.height(11).unwrap(), 0);
+        assert_eq!(tree.deepest_node(0).unwrap(), 2);
+        assert_eq!(tree.deepest_node(7).unwrap(), 8);
+    }
+
+    #[test]
+    fn level_next_and_prev() {
+        let tree = navigation_tree();
+        assert_eq!(tree.level_next(2).unwrap(), 4);
+        assert_eq!(tree.level_next(4).unwrap(), 8);
+        assert_eq!(tree.level_next(1).unwrap(), 7);
+        assert_eq!(tree.level_next(8).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_prev(8).unwrap(), 4);
+        assert_eq!(tree.level_prev(11).unwrap(), 7);
+        assert_eq!(tree.level_prev(1).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_prev(0).unwrap_err(), NodeError::NoSuchNodeError);
+    }
+
+    #[test]
+    fn to_id_tree_without_labels() {
+        let tree: BPTree<String> = BPTree::from_bitvec(bit_vec![true, false]).unwrap();
+        assert_eq!(tree.to_id_tree().unwrap_err(), NodeError::NoLabelError);
+    }
+
+    proptest! {
+        #[test]
+        fn strict_indices(id_tree in arb_id_tree(100)) {
+            let tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
+            let root = tree.root();
+            let nodes: Vec<u64> = tree.preorder(root).unwrap().collect();
+            let checks: Vec<IndexCheck> = vec![
+                ("is_leaf", Box::new(|i| tree.is_leaf(i).map(|_| ()))),
+                ("parent", Box::new(|i| tree.parent(i).map(|_| ()))),
+                ("first_child", Box::new(|i| tree.first_child(i).map(|_| ()))),
+                ("next_sibling", Box::new(|i| tree.next_sibling(i).map(|_| ()))),
+                ("child_label", Box::new(|i| tree.child_label(i).map(|_| ()))),
+                ("labeled_child", Box::new(|i| tree.labeled_child(i, 0).map(|_| ()))),
+                ("pre_rank", Box::new(|i| tree.pre_rank(i).map(|_| ()).ok_or(NodeError::NotANodeError))),
+                ("node_to_rank", Box::new(|i| tree.node_to_rank(i).map(|_| ()))),
+                ("ancestor", Box::new(|i| tree.ancestor(i, root).map(|_| ()))),
+                ("descendant", Box::new(|i| tree.ancestor(root, i).map(|_| ()))),
+                ("depth", Box::new(|i| tree.depth(i).map(|_| ()))),
+                ("subtree_size", Box::new(|i| tree.subtree_size(i).map(|_| ()))),
+                ("prev_sibling", Box::new(|i| tree.prev_sibling(i).map(|_| ()))),
+                ("last_child", Box::new(|i| tree.last_child(i).map(|_| ()))),
+                ("child", Box::new(|i| tree.child(i, 1).map(|_| ()))),
+                ("degree", Box::new(|i| tree.degree(i).map(|_| ()))),
+                ("child_rank", Box::new(|i| tree.child_rank(i).map(|_| ()).ok_or(NodeError::NotANodeError))),
+                ("level_ancestor", Box::new(|i| tree.level_ancestor(i, 0).map(|_| ()))),
+                ("lca", Box::new(|i| tree.lca(i, root).map(|_| ()))),
+                ("post_rank", Box::new(|i| tree.post_rank(i).map(|_| ()))),
+                ("leaf_rank", Box::new(|i| tree.leaf_rank(i).map(|_| ()))),
+                ("height", Box::new(|i| tree.height(i).map(|_| ()))),
+                ("deepest_node", Box::new(|i| tree.deepest_node(i).map(|_| ()))),
+                ("level_next", Box::new(|i| tree.level_next(i).map(|_| ()))),
+                ("level_prev", Box::new(|i| tree.level_prev(i).map(|_| ()))),
+                ("children", Box::new(|i| tree.children(i).map(|_| ()))),
+            ];
+            check_node_indices(tree.rankselect.len() + 2, &nodes, &checks)?;
+        }
+
+        #[test]
+        fn node_ranks(id_tree in arb_id_tree(300)) {
+            let tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
+            let nodes: Vec<u64> = tree.preorder(tree.root()).unwrap().collect();
+            for (rank, index) in nodes.iter().enumerate() {
+                prop_assert_eq!(tree.node_to_rank(*index), Ok(NodeRank(rank as u64)));
+                prop_assert_eq!(tree.rank_to_node(NodeRank(rank as u64)), Ok(*index));
+            }
+            prop_assert_eq!(
+                tree.rank_to_node(NodeRank(nodes.len() as u64)),
+                Err(NodeError::NoSuchNodeError)
+            );
+            for index in 0..tree.rankselect.len() + 1 {
+                if !nodes.contains(&index) {
+                    prop_assert_eq!(tree.node_to_rank(index), Err(NodeError::NotANodeError));
+                }
+            }
+        }
+
+        #[test]
+        fn to_louds(id_tree in arb_id_tree(1500)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
+            let converted = tree.to_louds();
+            prop_assert_eq!(pre_order_shape(&converted.to_id_tree().unwrap()), shape);
+            prop_assert_eq!(converted.to_bp(), tree);
+        }
+
+        #[test]
+        fn child_rank_matches_louds(id_tree in arb_id_tree(300)) {
+            let tree = BPTree::<_>::from_id_tree(id_tree.clone()).unwrap();
+            let louds = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let bp_nodes = tree.preorder(tree.root()).unwrap();
+            let louds_nodes = louds.preorder(louds.root()).unwrap();
+            for (bp_node, louds_node) in bp_nodes.zip(louds_nodes) {
+                prop_assert_eq!(tree.child_rank(bp_node), louds.child_rank(louds_node));
+            }
+        }
+
+        #[test]
+        fn navigation(id_tree in arb_id_tree(400), block_size in 1u64..=16) {
+            // pre-order numbers of the parents, depths and children of all nodes
+            let root_id = id_tree.root_node_id().unwrap().clone();
+            let ids: Vec<NodeId> = id_tree.traverse_pre_order_ids(&root_id).unwrap().collect();
+            let number: HashMap<NodeId, usize> =
+                ids.iter().cloned().enumerate().map(|(k, id)| (id, k)).collect();
+            let children: Vec<Vec<usize>> = ids
+                .iter()
+                .map(|id| id_tree.children_ids(id).unwrap().map(|child| number[child]).collect())
+                .collect();
+            let mut parent = vec![None; ids.len()];
+            let mut depth = vec![1; ids.len()];
+            for k in 0..ids.len() {
+                for child in &children[k] {
+                    parent[*child] = Some(k);
+                    depth[*child] = depth[k] + 1;
+                }
+            }
+            let mut size = vec![1; ids.len()];
+            for k in (0..ids.len()).rev() {
+                if let Some(p) = parent[k] {
+                    size[p] += size[k];
+                }
+            }
+            let post: Vec<usize> = id_tree
+                .traverse_post_order_ids(&root_id)
+                .unwrap()
+                .map(|id| number[&id])
+                .collect();
+
+            // small blocks make the searches cross the MinMax heap
+            let tree: BPTree<_> = BPTreeBuilder::new()
+                .block_size(block_size)
+                .build_from_id_tree(id_tree)
+                .unwrap();
+            let index: Vec<u64> = (0..ids.len())
+                .map(|k| tree.pre_select(k as u64 + 1).unwrap())
+                .collect();
+            let mut leaves = 0;
+            for k in 0..ids.len() {
+                let v = index[k];
+                prop_assert_eq!(tree.degree(v).unwrap(), children[k].len() as u64);
+                for (n, child) in children[k].iter().enumerate() {
+                    let n = n as u64 + 1;
+                    prop_assert_eq!(tree.child(v, n).unwrap(), index[*child]);
+                    prop_assert_eq!(tree.child_rank(index[*child]).unwrap(), n - 1);
+                    let prev = tree.prev_sibling(index[*child]).ok();
+                    prop_assert_eq!(prev, if n > 1 { Some(tree.child(v, n - 1).unwrap()) } else { None });
+                }
+                prop_assert_eq!(tree.last_child(v).ok(), children[k].last().map(|c| index[*c]));
+                if children[k].is_empty() {
+                    leaves += 1;
+                    prop_assert_eq!(tree.leaf_select(leaves).unwrap(), v);
+                }
+                prop_assert_eq!(tree.leaf_rank(v).unwrap(), leaves);
+                let post_rank = post.iter().position(|p| *p == k).unwrap() as u64 + 1;
+                prop_assert_eq!(tree.post_rank(v).unwrap(), post_rank);
+                prop_assert_eq!(tree.post_select(post_rank).unwrap(), v);
+
+                let subtree = k..k + size[k];
+                let max_depth = subtree.clone().map(|d| depth[d]).max().unwrap();
+                let deepest = subtree.clone().find(|d| depth[*d] == max_depth).unwrap();
+                prop_assert_eq!(tree.height(v).unwrap(), (max_depth - depth[k]) as u64);
+                prop_assert_eq!(tree.deepest_node(v).unwrap(), index[deepest]);
+                let next = (k + 1..ids.len()).find(|d| depth[*d] == depth[k]);
+                prop_assert_eq!(tree.level_next(v).ok(), next.map(|d| index[d]));
+                let prev = (0..k).rev().find(|d| depth[*d] == depth[k]);
+                prop_assert_eq!(tree.level_prev(v).ok(), prev.map(|d| index[d]));
+
+                let mut ancestor = Some(k);
+                for distance in 0..=depth[k] as u64 {
+                    prop_assert_eq!(tree.level_ancestor(v, distance).ok(), ancestor.map(|a| index[a]));
+                    ancestor = ancestor.and_then(|a| parent[a]);
+                }
+                let other = (k * 7919 + 13) % ids.len();
+                let mut lca = k;
+                while !(lca..lca + size[lca]).contains(&other) {
+                    lca = parent[lca].unwrap();
+                }
+                prop_assert_eq!(tree.lca(v, index[other]).unwrap(), index[lca]);
+            }
+        }
+
+        #[test]
+        fn id_tree_round_trip(id_tree in arb_id_tree(300)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = BPTree::<_>::from_id_tree(id_tree).unwrap();
+            prop_assert_eq!(pre_order_shape(&tree.to_id_tree().unwrap()), shape);
+        }
+
+        #[test]
+        fn sorted_children(id_tree in arb_id_tree(300)) {
+            let id_tree = with_small_alphabet(id_tree, 6);
+            let mut unsorted = BPTree::<_>::from_id_tree(id_tree.clone()).unwrap();
+            let tree = BPTree::<_>::from_id_tree_sorted(id_tree).unwrap();
+            prop_assert!(tree.has_sorted_children());
+            prop_assert!(!unsorted.has_sorted_children());
+            let nodes: Vec<u64> = tree.preorder(tree.root()).unwrap().collect();
+            for index in nodes {
+                let labels: Vec<u32> = tree
+                    .children(index)
+                    .unwrap()
+                    .map(|child| *tree.child_label(child).unwrap())
+                    .collect();
+                prop_assert!(labels.windows(2).all(|pair| pair[0] <= pair[1]));
+                for label in 0..7 {
+                    prop_assert_eq!(
+                        tree.labeled_child(index, label).ok(),
+                        linear_labeled_child(&tree, index, label)
+                    );
+                }
+            }
+            let sorted = unsorted.enable_sorted_children();
+            prop_assert_eq!(sorted, unsorted.has_sorted_children());
+            prop_assert_eq!(sorted, children_sorted_by_label(&unsorted).unwrap());
+            prop_assert!(tree.to_louds().has_sorted_children());
+        }
     }
 }
@@ -0,0 +1,214 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Precomputed excess tables for bytes and popcount/select on 64 bit words, so that the
+//! bits inside of a `MinMax` block are scanned a byte or a word at a time. Bits are read
+//! from the least to the most significant one, like everywhere else in the crate.
+
+/// The excess of the 8 bits of a byte, a 1 counting +1 and a 0 counting -1. `min`, `max`
+/// and `min_count` refer to the excess after each of the bits, relative to the excess
+/// before the byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteExcess {
+    pub excess: i8,
+    pub min: i8,
+    pub max: i8,
+    pub min_count: u8,
+}
+
+impl ByteExcess {
+    /// Returns the values for a single bit.
+    pub fn of_bit(bit: bool) -> Self {
+        let excess = if bit { 1 } else { -1 };
+        Self {
+            excess,
+            min: excess,
+            max: excess,
+            min_count: 1,
+        }
+    }
+}
+
+/// The `ByteExcess` of every byte.
+pub static BYTE_EXCESS: [ByteExcess; 256] = byte_excess_table();
+
+/// `BYTE_SELECT[byte][k]` is the position of the (k+1)-th 1 in `byte`, or 8 if there is none.
+static BYTE_SELECT: [[u8; 8]; 256] = byte_select_table();
+
+const fn byte_excess_table() -> [ByteExcess; 256] {
+    let mut table = [ByteExcess {
+        excess: 0,
+        min: 0,
+        max: 0,
+        min_count: 0,
+    }; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let (mut excess, mut min, mut max, mut min_count) = (0i8, i8::MAX, i8::MIN, 0u8);
+        let mut bit = 0;
+        while bit < 8 {
+            excess += if byte >> bit & 1 == 1 { 1 } else { -1 };
+            if excess < min {
+                min = excess;
+                min_count = 1;
+            } else if excess == min {
+                min_count += 1;
+            }
+            if excess > max {
+                max = excess;
+            }
+            bit += 1;
+        }
+        table[byte] = ByteExcess {
+            excess,
+            min,
+            max,
+            min_count,
+        };
+        byte += 1;
+    }
+    table
+}
+
+const fn byte_select_table() -> [[u8; 8]; 256] {
+    let mut table = [[8u8; 8]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let (mut bit, mut rank) = (0, 0);
+        while bit < 8 {
+            if byte >> bit & 1 == 1 {
+                table[byte][rank] = bit as u8;
+                rank += 1;
+            }
+            bit += 1;
+        }
+        byte += 1;
+    }
+    table
+}
+
+/// Returns the number of 1s in the bits `[from, to)` of `words`.
+pub fn popcount(words: &[u64], from: u64, to: u64) -> u64 {
+    if from >= to {
+        return 0;
+    }
+    let (first, last) = ((from / 64) as usize, ((to - 1) / 64) as usize);
+    let low_mask = !0u64 << (from % 64);
+    let high_mask = !0u64 >> (63 - (to - 1) % 64);
+    if first == last {
+        return u64::from((words[first] & low_mask & high_mask).count_ones());
+    }
+    let mut count = (words[first] & low_mask).count_ones() + (words[last] & high_mask).count_ones();
+    for word in &words[first + 1..last] {
+        count += word.count_ones();
+    }
+    u64::from(count)
+}
+
+/// Returns the position of the `rank`-th 1 in `word`, starting with 1, or `None` if the
+/// word contains less than `rank` 1s.
+pub fn select_in_word(word: u64, rank: u32) -> Option<u32> {
+    if rank == 0 || rank > word.count_ones() {
+        return None;
+    }
+    let mut remaining = rank;
+    let mut offset = 0;
+    loop {
+        let byte = (word >> offset) as u8;
+        let ones = byte.count_ones();
+        if remaining <= ones {
+            let position = BYTE_SELECT[byte as usize][(remaining - 1) as usize];
+            return Some(offset + u32::from(position));
+        }
+        remaining -= ones;
+        offset += 8;
+    }
+}
+
+/// Returns the position of the `rank`-th 1 (or 0 if `bit` is false) in the bits
+/// `[from, to)` of `words`, starting with 1. Whole words are skipped using their popcount.
+pub fn select_in_range(words: &[u64], from: u64, to: u64, bit: bool, rank: u64) -> Option<u64> {
+    let mut remaining = rank;
+    let mut start = from;
+    while start < to {
+        let word_start = start - start % 64;
+        let end = (word_start + 64).min(to);
+        let mut word = if bit {
+            words[(start / 64) as usize]
+        } else {
+            !words[(start / 64) as usize]
+        };
+        word &= !0u64 << (start % 64);
+        if end - word_start < 64 {
+            word &= (1u64 << (end - word_start)) - 1;
+        }
+        let ones = u64::from(word.count_ones());
+        if remaining <= ones {
+            return select_in_word(word, remaining as u32).map(|p| word_start + u64::from(p));
+        }
+        remaining -= ones;
+        start = end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn byte_excess() {
+        assert_eq!(
+            BYTE_EXCESS[0b1111_1111],
+            ByteExcess {
+                excess: 8,
+                min: 1,
+                max: 8,
+                min_count: 1
+            }
+        );
+        // bits 1, 0, 0, 1, 0, 1, 1, 0 from the least significant one
+        assert_eq!(
+            BYTE_EXCESS[0b0110_1001],
+            ByteExcess {
+                excess: 0,
+                min: -1,
+                max: 1,
+                min_count: 2
+            }
+        );
+        assert_eq!(BYTE_EXCESS[0].min, -8);
+    }
+
+    #[test]
+    fn select() {
+        assert_eq!(select_in_word(0b1011_0000, 1), Some(4));
+        assert_eq!(select_in_word(0b1011_0000, 3), Some(7));
+        assert_eq!(select_in_word(0b1011_0000, 4), None);
+        assert_eq!(select_in_word(1 << 63, 1), Some(63));
+        assert_eq!(select_in_word(!0, 0), None);
+        let words = [!0u64, 0b101];
+        assert_eq!(select_in_range(&words, 60, 67, true, 6), Some(66));
+        assert_eq!(select_in_range(&words, 60, 66, true, 6), None);
+        assert_eq!(select_in_range(&words, 60, 67, false, 1), Some(65));
+    }
+
+    proptest! {
+        #[test]
+        fn word_ops(words in prop::collection::vec(any::<u64>(), 1..4), a in 0u64..256,
+                    b in 0u64..256, rank in 1u64..100) {
+            let len = words.len() as u64 * 64;
+            let (from, to) = (a.min(b) % len, a.max(b) % len);
+            let (from, to) = (from.min(to), from.max(to));
+            let bit = |index: u64| words[(index / 64) as usize] >> (index % 64) & 1 == 1;
+            prop_assert_eq!(popcount(&words, from, to), (from..to).filter(|i| bit(*i)).count() as u64);
+            for value in [true, false].iter() {
+                let expected = (from..to).filter(|i| bit(*i) == *value).nth(rank as usize - 1);
+                prop_assert_eq!(select_in_range(&words, from, to, *value, rank), expected);
+            }
+        }
+    }
+}
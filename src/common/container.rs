@@ -0,0 +1,791 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Self-describing container format used by `save_to` and `from_file` of the trees.
+//!
+//! A container starts with a fixed header followed by a section table and the sections.
+//! All integers are stored in little endian byte order.
+//!
+//! | Offset | Size | Content                                        |
+//! |--------|------|------------------------------------------------|
+//! | 0      | 8    | Magic number `SUCCTREE`                        |
+//! | 8      | 4    | Format version                                 |
+//! | 12     | 1    | Tree kind (see `TreeKind`)                     |
+//! | 13     | 1    | Label codec (see `LabelCodec`)                 |
+//! | 14     | 2    | Number of sections                             |
+//! | 16     | 8    | Number of nodes                                |
+//! | 24     | 8    | Length of the bit vector                       |
+//! | 32     | 4    | CRC-32 of the whole file, with this field zero |
+//! | 36     | 4    | Reserved                                       |
+//! | 40     | 24n  | Section table: kind (4), reserved (4), offset (8), length (8) |
+//!
+//! Every section starts at an offset that is a multiple of 8. The bits, the rank directory
+//! and the MinMax heap are stored as arrays of little endian 64 bit values, so a memory-mapped
+//! container can be used directly without decoding (see `Container::open_mapped`).
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::common::container::{Container, ContainerWriter, TreeKind};
+//! use fp_succinct_trees_1::common::rank_select::RankSelect;
+//!
+//! let mut writer = ContainerWriter::new(TreeKind::BP, 1, 2);
+//! writer.add_rank_select(&RankSelect::new(&bit_vec![true, false]));
+//! let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+//! assert_eq!(container.header().node_count, 1);
+//! assert_eq!(container.rank_select().unwrap().rank_1(1), Some(1));
+//! # }
+//! ```
+
+use bincode::{deserialize, serialize};
+use common::errors::FileError;
+use common::min_max::MinMax;
+use common::rank_select::RankSelect;
+use common::storage::{Plain, Storage};
+use memmap2::Mmap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::mem;
+use std::sync::Arc;
+
+/// Magic number at the beginning of every container.
+pub const MAGIC: [u8; 8] = *b"SUCCTREE";
+/// Version of the container format written by `ContainerWriter`.
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 40;
+const SECTION_ENTRY_SIZE: usize = 24;
+const CRC_OFFSET: usize = 32;
+const SECTION_ALIGNMENT: usize = 8;
+
+/// The kind of tree stored in a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeKind {
+    BP,
+    LOUDS,
+    DFUDS,
+    /// A `LOUDSTrie`, i.e. a LOUDS tree with terminal flags.
+    Trie,
+    /// A `SuccinctMap`, i.e. a trie with a value per key.
+    Map,
+}
+
+impl TreeKind {
+    fn id(self) -> u8 {
+        match self {
+            TreeKind::BP => 1,
+            TreeKind::LOUDS => 2,
+            TreeKind::DFUDS => 3,
+            TreeKind::Trie => 4,
+            TreeKind::Map => 5,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, FileError> {
+        match id {
+            1 => Ok(TreeKind::BP),
+            2 => Ok(TreeKind::LOUDS),
+            3 => Ok(TreeKind::DFUDS),
+            4 => Ok(TreeKind::Trie),
+            5 => Ok(TreeKind::Map),
+            _ => Err(FileError::UnknownTreeKindError(id)),
+        }
+    }
+}
+
+/// The encoding of the labels section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelCodec {
+    /// The tree has no labels, there is no labels section.
+    None,
+    /// The labels are stored as a bincode encoded `Vec<L>`.
+    Bincode,
+}
+
+impl LabelCodec {
+    fn id(self) -> u8 {
+        match self {
+            LabelCodec::None => 0,
+            LabelCodec::Bincode => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, FileError> {
+        match id {
+            0 => Ok(LabelCodec::None),
+            1 => Ok(LabelCodec::Bincode),
+            _ => Err(FileError::UnsupportedLabelCodecError(id)),
+        }
+    }
+}
+
+/// The kinds of sections a container may contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectionKind {
+    /// The bit vector of the tree, packed into 64 bit words.
+    Bits,
+    /// The labels of the tree, encoded as given by the `LabelCodec`.
+    Labels,
+    /// The block size of the MinMax structure followed by the nodes of its heap.
+    MinMax,
+    /// The superblocks of the rank/select structure.
+    RankDirectory,
+    /// The terminal flags of a trie, one bit per node in level order, packed into 64 bit words.
+    Terminals,
+    /// The superblocks of the rank/select structure of the terminal flags.
+    TerminalDirectory,
+    /// The values of a map as a bincode encoded `Vec<V>`, ordered by the ids of the keys.
+    Values,
+}
+
+impl SectionKind {
+    fn id(self) -> u32 {
+        match self {
+            SectionKind::Bits => 1,
+            SectionKind::Labels => 2,
+            SectionKind::MinMax => 3,
+            SectionKind::RankDirectory => 4,
+            SectionKind::Terminals => 5,
+            SectionKind::TerminalDirectory => 6,
+            SectionKind::Values => 7,
+        }
+    }
+
+    /// Sections with unknown ids are skipped, so newer writers may add optional sections.
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            1 => Some(SectionKind::Bits),
+            2 => Some(SectionKind::Labels),
+            3 => Some(SectionKind::MinMax),
+            4 => Some(SectionKind::RankDirectory),
+            5 => Some(SectionKind::Terminals),
+            6 => Some(SectionKind::TerminalDirectory),
+            7 => Some(SectionKind::Values),
+            _ => None,
+        }
+    }
+}
+
+/// The header of a container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub kind: TreeKind,
+    pub label_codec: LabelCodec,
+    pub node_count: u64,
+    pub bit_len: u64,
+}
+
+/// Builds a container from its sections.
+pub struct ContainerWriter {
+    kind: TreeKind,
+    label_codec: LabelCodec,
+    node_count: u64,
+    bit_len: u64,
+    sections: Vec<(SectionKind, Vec<u8>)>,
+}
+
+impl ContainerWriter {
+    /// Creates a writer for a tree without labels.
+    /// # Arguments
+    /// * `kind` The kind of the tree
+    /// * `node_count` The number of nodes of the tree
+    /// * `bit_len` The length of the bit vector of the tree
+    pub fn new(kind: TreeKind, node_count: u64, bit_len: u64) -> Self {
+        Self {
+            kind,
+            label_codec: LabelCodec::None,
+            node_count,
+            bit_len,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Sets the codec of the labels section.
+    pub fn set_label_codec(&mut self, codec: LabelCodec) {
+        self.label_codec = codec;
+    }
+
+    /// Adds a section. Sections are written in the order they were added.
+    pub fn add_section(&mut self, kind: SectionKind, data: Vec<u8>) {
+        self.sections.push((kind, data));
+    }
+
+    /// Adds the bits and the rank directory of a rank/select structure.
+    pub fn add_rank_select(&mut self, rankselect: &RankSelect) {
+        self.add_section(SectionKind::Bits, rankselect.words().encode());
+        self.add_section(
+            SectionKind::RankDirectory,
+            rankselect.superblocks().encode(),
+        );
+    }
+
+    /// Adds the terminal flags of a trie and their rank directory.
+    pub fn add_terminals(&mut self, terminals: &RankSelect) {
+        self.add_section(SectionKind::Terminals, terminals.words().encode());
+        self.add_section(
+            SectionKind::TerminalDirectory,
+            terminals.superblocks().encode(),
+        );
+    }
+
+    /// Adds the values section of a map.
+    pub fn add_values<V: Serialize>(&mut self, values: &[V]) -> Result<(), FileError> {
+        self.add_section(SectionKind::Values, serialize(values)?);
+        Ok(())
+    }
+
+    /// Adds the block size and the heap of a MinMax structure. Its bits are not stored again,
+    /// they are taken from the bits section.
+    pub fn add_minmax(&mut self, minmax: &MinMax) {
+        let mut data = Vec::with_capacity(8 + minmax.heap().len() * 40);
+        minmax.block_size().write_le(&mut data);
+        data.extend_from_slice(&minmax.heap().encode());
+        self.add_section(SectionKind::MinMax, data);
+    }
+
+    /// Adds the labels section and sets the label codec accordingly. Nothing is added for
+    /// trees without labels.
+    pub fn add_labels<L: Serialize>(&mut self, labels: &[L]) -> Result<(), FileError> {
+        if !labels.is_empty() {
+            self.set_label_codec(LabelCodec::Bincode);
+            self.add_section(SectionKind::Labels, serialize(labels)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the complete container including the checksum.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let table_end = HEADER_SIZE + SECTION_ENTRY_SIZE * self.sections.len();
+        let mut bytes = Vec::with_capacity(table_end);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.push(self.kind.id());
+        bytes.push(self.label_codec.id());
+        bytes.extend_from_slice(&(self.sections.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.node_count.to_le_bytes());
+        bytes.extend_from_slice(&self.bit_len.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+
+        let mut offset = align(table_end);
+        for (kind, data) in &self.sections {
+            bytes.extend_from_slice(&kind.id().to_le_bytes());
+            bytes.extend_from_slice(&[0; 4]);
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            offset = align(offset + data.len());
+        }
+        for (_, data) in &self.sections {
+            bytes.resize(align(bytes.len()), 0);
+            bytes.extend_from_slice(data);
+        }
+
+        let crc = checksum(&bytes);
+        bytes[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Writes the container to a file
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    pub fn write_to(&self, path: &str) -> Result<(), FileError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&self.to_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// A validated container whose sections can be accessed by kind.
+pub struct Container {
+    header: Header,
+    backing: Backing,
+    sections: Vec<(SectionKind, usize, usize)>,
+}
+
+enum Backing {
+    Owned(Vec<u8>),
+    Mapped(Arc<Mmap>),
+}
+
+impl Container {
+    /// Reads and validates a container from a file
+    /// # Arguments
+    /// * `path` The path of the file to read
+    /// * `expected` The kind of tree the file has to contain
+    /// # Errors
+    /// See `from_bytes`, additionally `IoError` if the file cannot be read.
+    pub fn from_file(path: &str, expected: TreeKind) -> Result<Self, FileError> {
+        Self::from_bytes(fs::read(path)?, expected)
+    }
+
+    /// Validates a container given as bytes
+    /// # Arguments
+    /// * `bytes` The contents of the container
+    /// * `expected` The kind of tree the container has to contain
+    /// # Errors
+    /// * `NotATreeFileError` If the magic number is missing.
+    /// * `UnsupportedVersionError` If the container was written in another format version.
+    /// * `UnknownTreeKindError` If the tree kind is unknown.
+    /// * `WrongTreeKindError` If the container holds another kind of tree.
+    /// * `UnsupportedLabelCodecError` If the label codec is unknown.
+    /// * `TruncatedFileError` If the header or a section lies outside of the container.
+    /// * `ChecksumError` If the checksum does not match.
+    pub fn from_bytes(bytes: Vec<u8>, expected: TreeKind) -> Result<Self, FileError> {
+        let container = Self::parse(Backing::Owned(bytes), expected)?;
+        container.verify_checksum()?;
+        Ok(container)
+    }
+
+    /// Maps a container file into memory. Only the header and the section table are read,
+    /// all sections are loaded lazily by the operating system on first access. The checksum
+    /// is not verified, as that would require reading the whole file; call `verify_checksum`
+    /// to do so explicitly.
+    /// # Arguments
+    /// * `path` The path of the file to map
+    /// * `expected` The kind of tree the file has to contain
+    /// # Errors
+    /// See `from_bytes`, except for `ChecksumError`.
+    /// # Safety
+    /// The file must not be modified or truncated while the container or any structure
+    /// loaded from it is alive, otherwise reading from it is undefined behavior.
+    pub unsafe fn open_mapped(path: &str, expected: TreeKind) -> Result<Self, FileError> {
+        let map = Mmap::map(&File::open(path)?)?;
+        Self::parse(Backing::Mapped(Arc::new(map)), expected)
+    }
+
+    fn parse(backing: Backing, expected: TreeKind) -> Result<Self, FileError> {
+        let (header, sections) = {
+            let bytes = backing.bytes();
+            if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+                return Err(FileError::NotATreeFileError);
+            }
+            if bytes.len() < HEADER_SIZE {
+                return Err(FileError::TruncatedFileError);
+            }
+            let version = read_u32(bytes, 8);
+            if version != FORMAT_VERSION {
+                return Err(FileError::UnsupportedVersionError(version));
+            }
+
+            let kind = TreeKind::from_id(bytes[12])?;
+            if kind != expected {
+                return Err(FileError::WrongTreeKindError {
+                    expected,
+                    found: kind,
+                });
+            }
+            let header = Header {
+                version,
+                kind,
+                label_codec: LabelCodec::from_id(bytes[13])?,
+                node_count: read_u64(bytes, 16),
+                bit_len: read_u64(bytes, 24),
+            };
+
+            let section_count = u16::from_le_bytes([bytes[14], bytes[15]]) as usize;
+            if bytes.len() < HEADER_SIZE + section_count * SECTION_ENTRY_SIZE {
+                return Err(FileError::TruncatedFileError);
+            }
+            let mut sections = Vec::with_capacity(section_count);
+            for i in 0..section_count {
+                let entry = HEADER_SIZE + i * SECTION_ENTRY_SIZE;
+                let offset = read_u64(bytes, entry + 8);
+                let len = read_u64(bytes, entry + 16);
+                match offset.checked_add(len) {
+                    Some(end) if end <= bytes.len() as u64 => {}
+                    _ => return Err(FileError::TruncatedFileError),
+                }
+                if let Some(kind) = SectionKind::from_id(read_u32(bytes, entry)) {
+                    sections.push((kind, offset as usize, len as usize));
+                }
+            }
+            (header, sections)
+        };
+
+        Ok(Self {
+            header,
+            backing,
+            sections,
+        })
+    }
+
+    /// Verifies the checksum of the whole container.
+    /// # Errors
+    /// * `ChecksumError` If the checksum does not match.
+    pub fn verify_checksum(&self) -> Result<(), FileError> {
+        let bytes = self.backing.bytes();
+        if checksum(bytes) == read_u32(bytes, CRC_OFFSET) {
+            Ok(())
+        } else {
+            Err(FileError::ChecksumError)
+        }
+    }
+
+    /// Returns the header of the container.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns whether the container is backed by a memory-mapped file.
+    pub fn is_mapped(&self) -> bool {
+        match self.backing {
+            Backing::Owned(_) => false,
+            Backing::Mapped(_) => true,
+        }
+    }
+
+    /// Returns the contents of the first section of the given kind, if there is one.
+    pub fn section(&self, kind: SectionKind) -> Option<&[u8]> {
+        self.section_range(kind)
+            .map(|(offset, len)| &self.backing.bytes()[offset..offset + len])
+    }
+
+    fn section_range(&self, kind: SectionKind) -> Option<(usize, usize)> {
+        self.sections
+            .iter()
+            .find(|&&(section_kind, _, _)| section_kind == kind)
+            .map(|&(_, offset, len)| (offset, len))
+    }
+
+    /// Returns the contents of a section that has to be present.
+    /// # Errors
+    /// * `MissingSectionError` If there is no section of this kind.
+    pub fn required_section(&self, kind: SectionKind) -> Result<&[u8], FileError> {
+        self.section(kind)
+            .ok_or(FileError::MissingSectionError(kind))
+    }
+
+    /// Returns the elements stored in a section, starting at byte `skip` of the section.
+    /// For memory-mapped containers the elements are not copied.
+    /// # Errors
+    /// * `MissingSectionError` If there is no section of this kind.
+    /// * `MalformedSectionError` If the section does not consist of whole elements.
+    fn storage<T: Plain>(&self, kind: SectionKind, skip: usize) -> Result<Storage<T>, FileError> {
+        let (offset, len) = self
+            .section_range(kind)
+            .ok_or(FileError::MissingSectionError(kind))?;
+        let size = mem::size_of::<T>();
+        if len < skip || !(len - skip).is_multiple_of(size) {
+            return Err(FileError::MalformedSectionError(kind));
+        }
+        let (offset, count) = (offset + skip, (len - skip) / size);
+        let mapped = match self.backing {
+            Backing::Mapped(ref map) => Storage::mapped(map, offset, count),
+            Backing::Owned(_) => None,
+        };
+        Ok(mapped
+            .unwrap_or_else(|| Storage::decode(&self.backing.bytes()[offset..offset + len - skip])))
+    }
+
+    /// Returns the rank/select structure stored in the `Bits` and `RankDirectory` sections.
+    /// # Errors
+    /// * `MissingSectionError` If one of the sections is missing.
+    /// * `MalformedSectionError` If the sizes of the sections do not match the header.
+    pub fn rank_select(&self) -> Result<RankSelect, FileError> {
+        self.rank_select_in(
+            SectionKind::Bits,
+            SectionKind::RankDirectory,
+            self.header.bit_len,
+        )
+    }
+
+    /// Returns the terminal flags stored in the `Terminals` and `TerminalDirectory` sections,
+    /// one for each node.
+    /// # Errors
+    /// * `MissingSectionError` If one of the sections is missing.
+    /// * `MalformedSectionError` If the sizes of the sections do not match the node count.
+    pub fn terminals(&self) -> Result<RankSelect, FileError> {
+        self.rank_select_in(
+            SectionKind::Terminals,
+            SectionKind::TerminalDirectory,
+            self.header.node_count,
+        )
+    }
+
+    fn rank_select_in(
+        &self,
+        bits: SectionKind,
+        directory: SectionKind,
+        len: u64,
+    ) -> Result<RankSelect, FileError> {
+        let words = self.storage(bits, 0)?;
+        let superblocks = self.storage(directory, 0)?;
+        RankSelect::from_parts(len, words, superblocks)
+            .ok_or(FileError::MalformedSectionError(bits))
+    }
+
+    /// Returns the MinMax structure stored in the `MinMax` section, or `None` if the container
+    /// has no such section. The bits are shared with the given rank/select structure.
+    /// # Errors
+    /// * `MalformedSectionError` If the heap does not match the bits.
+    pub fn minmax(&self, rankselect: &RankSelect) -> Result<Option<MinMax>, FileError> {
+        let block_size = match self.section(SectionKind::MinMax) {
+            Some(bytes) if bytes.len() >= 8 => u64::read_le(bytes),
+            Some(_) => return Err(FileError::MalformedSectionError(SectionKind::MinMax)),
+            None => return Ok(None),
+        };
+        let heap = self.storage(SectionKind::MinMax, 8)?;
+        MinMax::from_parts(
+            rankselect.len(),
+            rankselect.words().clone(),
+            block_size,
+            heap,
+        )
+        .map(Some)
+        .ok_or(FileError::MalformedSectionError(SectionKind::MinMax))
+    }
+
+    /// Decodes the values section of a map.
+    /// # Errors
+    /// * `MissingSectionError` If there is no values section.
+    /// * `SerializationError` If the values cannot be deserialized.
+    pub fn values<V: DeserializeOwned>(&self) -> Result<Vec<V>, FileError> {
+        Ok(deserialize(self.required_section(SectionKind::Values)?)?)
+    }
+
+    /// Decodes the labels section according to the label codec of the header.
+    /// # Errors
+    /// * `MissingSectionError` If the header announces labels, but there is no labels section.
+    /// * `SerializationError` If the labels cannot be deserialized.
+    /// * `LabelCountError` If there are labels, but not one for every node.
+    pub fn labels<L: DeserializeOwned>(&self) -> Result<Vec<L>, FileError> {
+        let labels: Vec<L> = match self.header.label_codec {
+            LabelCodec::None => return Ok(Vec::new()),
+            LabelCodec::Bincode => deserialize(self.required_section(SectionKind::Labels)?)?,
+        };
+        if labels.len() as u64 != self.header.node_count {
+            return Err(FileError::LabelCountError {
+                expected: self.header.node_count,
+                found: labels.len() as u64,
+            });
+        }
+        Ok(labels)
+    }
+}
+
+impl Backing {
+    fn bytes(&self) -> &[u8] {
+        match *self {
+            Backing::Owned(ref bytes) => bytes,
+            Backing::Mapped(ref map) => map,
+        }
+    }
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buffer = [0; 4];
+    buffer.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buffer)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buffer = [0; 8];
+    buffer.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buffer)
+}
+
+/// CRC-32 of a container, computed as if the checksum field was zero.
+fn checksum(bytes: &[u8]) -> u32 {
+    crc32(&[&bytes[..CRC_OFFSET], &[0; 4], &bytes[CRC_OFFSET + 4..]])
+}
+
+/// CRC-32 (IEEE 802.3) of the concatenation of the given bytes.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+    !parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(!0u32, |crc, &byte| {
+            table[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_container() -> Vec<u8> {
+        let mut writer = ContainerWriter::new(TreeKind::LOUDS, 2, 4);
+        writer.set_label_codec(LabelCodec::Bincode);
+        writer.add_rank_select(&RankSelect::new(&bit_vec![true, true, false, false]));
+        writer.add_section(SectionKind::Labels, vec![1, 2, 3]);
+        writer.to_bytes()
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let container = Container::from_bytes(example_container(), TreeKind::LOUDS).unwrap();
+        assert_eq!(
+            *container.header(),
+            Header {
+                version: FORMAT_VERSION,
+                kind: TreeKind::LOUDS,
+                label_codec: LabelCodec::Bincode,
+                node_count: 2,
+                bit_len: 4,
+            }
+        );
+        assert_eq!(container.section(SectionKind::Labels).unwrap(), &[1, 2, 3]);
+        assert_eq!(container.section(SectionKind::MinMax), None);
+        assert_eq!(
+            container.rank_select().unwrap().to_bitvec(),
+            bit_vec![true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn minmax() {
+        let bits = bit_vec![true, true, false, true, false, false];
+        let rankselect = RankSelect::new(&bits);
+        let mut writer = ContainerWriter::new(TreeKind::BP, 3, 6);
+        writer.add_rank_select(&rankselect);
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        assert!(container.minmax(&rankselect).unwrap().is_none());
+
+        writer.add_minmax(&MinMax::new(bits, 2));
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        let minmax = container.minmax(&rankselect).unwrap().unwrap();
+        assert_eq!(minmax.block_size(), 2);
+        assert_eq!(minmax.find_close(1).unwrap(), 2);
+        assert_eq!(minmax.find_close(0).unwrap(), 5);
+    }
+
+    #[test]
+    fn malformed_rank_directory() {
+        let mut writer = ContainerWriter::new(TreeKind::BP, 1, 2);
+        writer.add_section(SectionKind::Bits, vec![1, 0, 0, 0, 0, 0, 0, 0]);
+        writer.add_section(SectionKind::RankDirectory, vec![0; 4]);
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        match container.rank_select() {
+            Err(FileError::MalformedSectionError(SectionKind::RankDirectory)) => {}
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn sections_are_aligned() {
+        let bytes = example_container();
+        let count = u16::from_le_bytes([bytes[14], bytes[15]]) as usize;
+        for i in 0..count {
+            assert_eq!(
+                read_u64(&bytes, HEADER_SIZE + i * SECTION_ENTRY_SIZE + 8) % 8,
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_magic() {
+        let mut bytes = example_container();
+        bytes[0] = b'X';
+        match Container::from_bytes(bytes, TreeKind::LOUDS) {
+            Err(FileError::NotATreeFileError) => {}
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn wrong_version() {
+        let mut bytes = example_container();
+        bytes[8] = 42;
+        match Container::from_bytes(bytes, TreeKind::LOUDS) {
+            Err(FileError::UnsupportedVersionError(42)) => {}
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let mut writer = ContainerWriter::new(TreeKind::BP, 2, 4);
+        writer
+            .add_labels(&["a".to_string(), "b".to_string()])
+            .unwrap();
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        assert_eq!(container.header().label_codec, LabelCodec::Bincode);
+        assert_eq!(container.labels::<String>().unwrap(), vec!["a", "b"]);
+
+        let mut writer = ContainerWriter::new(TreeKind::BP, 2, 4);
+        writer.add_labels::<String>(&[]).unwrap();
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        assert_eq!(container.header().label_codec, LabelCodec::None);
+        assert!(container.labels::<String>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn label_count() {
+        let mut writer = ContainerWriter::new(TreeKind::BP, 3, 6);
+        writer
+            .add_labels(&["a".to_string(), "b".to_string()])
+            .unwrap();
+        let container = Container::from_bytes(writer.to_bytes(), TreeKind::BP).unwrap();
+        match container.labels::<String>() {
+            Err(FileError::LabelCountError { expected, found }) => {
+                assert_eq!(expected, 3);
+                assert_eq!(found, 2);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wrong_kind() {
+        match Container::from_bytes(example_container(), TreeKind::BP) {
+            Err(FileError::WrongTreeKindError { expected, found }) => {
+                assert_eq!(expected, TreeKind::BP);
+                assert_eq!(found, TreeKind::LOUDS);
+            }
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn corrupt_section() {
+        let mut bytes = example_container();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        match Container::from_bytes(bytes, TreeKind::LOUDS) {
+            Err(FileError::ChecksumError) => {}
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = example_container();
+        match Container::from_bytes(bytes[..20].to_vec(), TreeKind::LOUDS) {
+            Err(FileError::TruncatedFileError) => {}
+            other => panic!("Unexpected result {:?}", other.err()),
+        }
+    }
+}
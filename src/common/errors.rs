@@ -1,29 +1,261 @@
-#[derive(Fail, Debug, PartialEq)]
-#[fail(display = "The supplied bitvector is not valid for this tree.")]
-pub struct InvalidBitvecError;
+use bincode;
+use common::container::{SectionKind, TreeKind};
+use std::error::Error;
+use std::fmt;
+use std::io;
 
-#[derive(Fail, Debug, PartialEq)]
-#[fail(display = "The supplied tree does not contain any nodes.")]
+/// The supplied bits stop being a valid tree at `position`, which is the length of the bits
+/// if they end too early.
+#[derive(Debug, PartialEq)]
+pub struct InvalidBitvecError {
+    pub position: u64,
+}
+
+impl fmt::Display for InvalidBitvecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The supplied bitvector is not valid for this tree at bit {}.",
+            self.position
+        )
+    }
+}
+
+impl Error for InvalidBitvecError {}
+
+#[derive(Debug, PartialEq)]
 pub struct EmptyTreeError;
 
-#[derive(Fail, Debug, PartialEq)]
+impl fmt::Display for EmptyTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The supplied tree does not contain any nodes.")
+    }
+}
+
+impl Error for EmptyTreeError {}
+
+#[derive(Debug, PartialEq)]
 pub enum NodeError {
-    #[fail(display = "The supplied index does not reference a node.")]
     NotANodeError,
-    #[fail(display = "The supplied index does not reference a leaf.")]
     NotALeafError,
-    #[fail(display = "The supplied index does not reference a node with leafs.")]
     NotAParentError,
-    #[fail(display = "The supplied index references the root node.")]
     RootNodeError,
-    #[fail(display = "The supplied index does not reference a node with a sibling.")]
     NoSiblingError,
-    #[fail(display = "The supplied index does not reference a node with a parent.")]
     HasNoParentError,
-    #[fail(display = "The supplied index does not reference a node with a next sibling.")]
     HasNoFurtherSiblingsError,
-    #[fail(display = "The supplied index has no label.")]
     NoLabelError,
-    #[fail(display = "There is no child which passes the requirements.")]
     NoSuchChildError,
+    NoSuchNodeError,
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match *self {
+            NodeError::NotANodeError => "The supplied index does not reference a node.",
+            NodeError::NotALeafError => "The supplied index does not reference a leaf.",
+            NodeError::NotAParentError => {
+                "The supplied index does not reference a node with leafs."
+            }
+            NodeError::RootNodeError => "The supplied index references the root node.",
+            NodeError::NoSiblingError => {
+                "The supplied index does not reference a node with a sibling."
+            }
+            NodeError::HasNoParentError => {
+                "The supplied index does not reference a node with a parent."
+            }
+            NodeError::HasNoFurtherSiblingsError => {
+                "The supplied index does not reference a node with a next sibling."
+            }
+            NodeError::NoLabelError => "The supplied index has no label.",
+            NodeError::NoSuchChildError => "There is no child which passes the requirements.",
+            NodeError::NoSuchNodeError => "There is no node which passes the requirements.",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for NodeError {}
+
+/// The error of every operation that reads or writes a tree, either as a file or as a
+/// parenthesis sequence. The offsets of the parse errors are byte offsets into the input.
+#[derive(Debug)]
+pub enum FileError {
+    IoError(io::Error),
+    InvalidCharacterError {
+        offset: u64,
+        character: char,
+    },
+    UnmatchedCloseError(u64),
+    UnexpectedEndError(u64),
+    TrailingDataError(u64),
+    NotATreeFileError,
+    UnsupportedVersionError(u32),
+    UnknownTreeKindError(u8),
+    WrongTreeKindError {
+        expected: TreeKind,
+        found: TreeKind,
+    },
+    UnsupportedLabelCodecError(u8),
+    TruncatedFileError,
+    ChecksumError,
+    MissingSectionError(SectionKind),
+    MalformedSectionError(SectionKind),
+    /// The stored bits stop being a valid tree at `position`.
+    InvalidBitvecError {
+        position: u64,
+    },
+    LabelCountError {
+        expected: u64,
+        found: u64,
+    },
+    InvalidTreeError,
+    SerializationError(bincode::Error),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileError::IoError(ref error) => write!(f, "Could not access the tree file: {}", error),
+            FileError::InvalidCharacterError { offset, character } => write!(
+                f,
+                "Unexpected character {:?} at byte {}.",
+                character, offset
+            ),
+            FileError::UnmatchedCloseError(offset) => write!(
+                f,
+                "The tree starts with a closing parenthesis at byte {}.",
+                offset
+            ),
+            FileError::UnexpectedEndError(offset) => write!(
+                f,
+                "The input ends at byte {} before the tree is closed.",
+                offset
+            ),
+            FileError::TrailingDataError(offset) => write!(
+                f,
+                "Unexpected data after the end of the tree at byte {}.",
+                offset
+            ),
+            FileError::NotATreeFileError => write!(f, "The file does not contain a succinct tree."),
+            FileError::UnsupportedVersionError(version) => {
+                write!(f, "Unsupported format version {}.", version)
+            }
+            FileError::UnknownTreeKindError(kind) => write!(f, "Unknown tree kind {}.", kind),
+            FileError::WrongTreeKindError { expected, found } => write!(
+                f,
+                "The file contains a {:?} tree, but a {:?} tree was expected.",
+                found, expected
+            ),
+            FileError::UnsupportedLabelCodecError(codec) => {
+                write!(f, "Unsupported label codec {}.", codec)
+            }
+            FileError::TruncatedFileError => {
+                write!(f, "The file is truncated or its section table is corrupt.")
+            }
+            FileError::ChecksumError => {
+                write!(f, "The checksum of the file does not match its contents.")
+            }
+            FileError::MissingSectionError(kind) => {
+                write!(
+                    f,
+                    "The file does not contain the required section {:?}.",
+                    kind
+                )
+            }
+            FileError::MalformedSectionError(kind) => {
+                write!(
+                    f,
+                    "The size of section {:?} does not match the header.",
+                    kind
+                )
+            }
+            FileError::InvalidBitvecError { position } => write!(
+                f,
+                "The bits stored in the file are not valid for this tree at bit {}.",
+                position
+            ),
+            FileError::LabelCountError { expected, found } => write!(
+                f,
+                "The file contains {} labels for {} nodes.",
+                found, expected
+            ),
+            FileError::InvalidTreeError => {
+                write!(
+                    f,
+                    "The tree stored in the file is not valid for this structure."
+                )
+            }
+            FileError::SerializationError(ref error) => {
+                write!(f, "Error while (de)serializing tree: {}", error)
+            }
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FileError::IoError(ref error) => Some(error),
+            FileError::SerializationError(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(error: io::Error) -> Self {
+        FileError::IoError(error)
+    }
+}
+
+impl From<InvalidBitvecError> for FileError {
+    fn from(error: InvalidBitvecError) -> Self {
+        FileError::InvalidBitvecError {
+            position: error.position,
+        }
+    }
+}
+
+impl From<bincode::Error> for FileError {
+    fn from(error: bincode::Error) -> Self {
+        FileError::SerializationError(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            NodeError::NotANodeError.to_string(),
+            "The supplied index does not reference a node."
+        );
+        assert_eq!(
+            FileError::InvalidBitvecError { position: 3 }.to_string(),
+            "The bits stored in the file are not valid for this tree at bit 3."
+        );
+        assert_eq!(
+            FileError::UnexpectedEndError(4).to_string(),
+            "The input ends at byte 4 before the tree is closed."
+        );
+        assert_eq!(
+            InvalidBitvecError { position: 2 }.to_string(),
+            "The supplied bitvector is not valid for this tree at bit 2."
+        );
+    }
+
+    #[test]
+    fn source() {
+        let error = FileError::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(error.source().unwrap().to_string(), "missing");
+        assert!(FileError::ChecksumError.source().is_none());
+        match FileError::from(InvalidBitvecError { position: 5 }) {
+            FileError::InvalidBitvecError { position: 5 } => {}
+            other => panic!("Unexpected error {:?}", other),
+        }
+        let boxed: Box<dyn Error> = Box::new(NodeError::NoLabelError);
+        assert_eq!(boxed.to_string(), "The supplied index has no label.");
+    }
 }
@@ -0,0 +1,526 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Stores for the labels of `LOUDSTree` and `BPTree`, chosen by the second generic parameter
+//! of the trees. `Vec<L>` keeps every label as it is and is the default. The compact stores
+//! keep every distinct label once and the labels of the nodes as codes of ⌈log σ⌉ bits for σ
+//! distinct labels:
+//!
+//! * `PackedLabels` for small alphabets, e.g. bytes, characters or small integers.
+//! * `DictionaryLabels` for repeated labels without an order, e.g. strings. The dictionary is
+//!   ordered by frequency, so frequent labels are found first.
+//! * `WaveletLabels` stores the codes in a wavelet matrix. Accessing a label takes O(log σ)
+//!   time, but the first occurrence of a label in a range is found in O(log σ) as well, which
+//!   `LOUDSTree::labeled_child` uses.
+//!
+//! Files written by `save_to` always contain the plain labels, so a tree can be loaded with a
+//! different store than it was saved with.
+//!
+//! Example
+//!
+//! ```
+//! extern crate fp_succinct_trees_1;
+//! extern crate id_tree;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::common::label_store::WaveletLabels;
+//! use fp_succinct_trees_1::common::succinct_tree::SuccinctTree;
+//! use fp_succinct_trees_1::louds_tree::LOUDSTree;
+//! use id_tree::InsertBehavior::{AsRoot, UnderNode};
+//! use id_tree::{Node, TreeBuilder};
+//!
+//! let mut id_tree = TreeBuilder::new().build();
+//! let root = id_tree.insert(Node::new(b'r'), AsRoot).unwrap();
+//! id_tree.insert(Node::new(b'a'), UnderNode(&root)).unwrap();
+//! id_tree.insert(Node::new(b'b'), UnderNode(&root)).unwrap();
+//! let tree: LOUDSTree<u8, WaveletLabels<u8>> = LOUDSTree::from_id_tree(id_tree).unwrap();
+//! assert_eq!(*tree.child_label(5).unwrap(), b'b');
+//! assert_eq!(tree.labeled_child(1, b'b').unwrap(), 5);
+//! # }
+//! ```
+
+use bv::BitVec;
+use common::rank_select::RankSelect;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+
+/// A read-only sequence of labels, addressed by the rank of the node in the order of the tree.
+pub trait LabelStore<L>: SpaceUsage + Sized {
+    /// Creates the store for the labels of all nodes, or an empty store for a tree without
+    /// labels.
+    fn from_labels(labels: Vec<L>) -> Self;
+
+    /// Returns the number of labels.
+    fn len(&self) -> u64;
+
+    /// Returns whether the store has no labels.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the label at `index`, or `None` if `index` is out of bounds.
+    fn get(&self, index: u64) -> Option<&L>;
+
+    /// Returns the first index in `[from, to)` with the given label.
+    fn find(&self, from: u64, to: u64, label: &L) -> Option<u64>
+    where
+        L: PartialEq,
+    {
+        (from..to.min(self.len())).find(|index| self.get(*index) == Some(label))
+    }
+
+    /// Returns all labels in order.
+    fn to_vec(&self) -> Vec<L>
+    where
+        L: Clone,
+    {
+        (0..self.len())
+            .map(|index| self.get(index).unwrap().clone())
+            .collect()
+    }
+}
+
+impl<L> SpaceUsage for Vec<L> {
+    /// Labels are counted with their inline size only.
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            labels: self.len() * mem::size_of::<L>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+impl<L> LabelStore<L> for Vec<L> {
+    fn from_labels(labels: Vec<L>) -> Self {
+        labels
+    }
+
+    fn len(&self) -> u64 {
+        Vec::len(self) as u64
+    }
+
+    fn get(&self, index: u64) -> Option<&L> {
+        self.as_slice().get(index as usize)
+    }
+}
+
+/// Unsigned integers of a fixed number of bits, packed into 64 bit words.
+#[derive(Clone, Debug)]
+struct PackedInts {
+    width: u64,
+    len: u64,
+    words: Vec<u64>,
+}
+
+impl PackedInts {
+    fn new<I: IntoIterator<Item = u64>>(width: u64, len: u64, values: I) -> Self {
+        let mut words = vec![0u64; (len * width).div_ceil(64) as usize];
+        if width == 0 {
+            return Self { width, len, words };
+        }
+        for (index, value) in values.into_iter().enumerate() {
+            let position = index as u64 * width;
+            let (word, offset) = ((position / 64) as usize, position % 64);
+            words[word] |= value << offset;
+            if offset + width > 64 {
+                words[word + 1] |= value >> (64 - offset);
+            }
+        }
+        Self { width, len, words }
+    }
+
+    fn get(&self, index: u64) -> u64 {
+        if self.width == 0 {
+            return 0;
+        }
+        let position = index * self.width;
+        let (word, offset) = ((position / 64) as usize, position % 64);
+        let mut value = self.words[word] >> offset;
+        if offset + self.width > 64 {
+            value |= self.words[word + 1] << (64 - offset);
+        }
+        if self.width < 64 {
+            value &= (1 << self.width) - 1;
+        }
+        value
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.words.len() * mem::size_of::<u64>()
+    }
+}
+
+/// Returns the number of bits needed for the codes `0..alphabet_size`.
+fn code_width(alphabet_size: usize) -> u64 {
+    u64::from(64 - (alphabet_size.max(1) as u64 - 1).leading_zeros())
+}
+
+/// Labels from a small alphabet, stored as bit-packed indices into the sorted alphabet.
+#[derive(Clone, Debug)]
+pub struct PackedLabels<L> {
+    alphabet: Vec<L>,
+    codes: PackedInts,
+}
+
+impl<L> SpaceUsage for PackedLabels<L> {
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            labels: self.alphabet.len() * mem::size_of::<L>() + self.codes.size_bytes(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+impl<L: Ord + Clone> LabelStore<L> for PackedLabels<L> {
+    fn from_labels(labels: Vec<L>) -> Self {
+        let mut alphabet = labels.clone();
+        alphabet.sort();
+        alphabet.dedup();
+        let codes = PackedInts::new(
+            code_width(alphabet.len()),
+            labels.len() as u64,
+            labels
+                .iter()
+                .map(|label| alphabet.binary_search(label).unwrap() as u64),
+        );
+        Self { alphabet, codes }
+    }
+
+    fn len(&self) -> u64 {
+        self.codes.len
+    }
+
+    fn get(&self, index: u64) -> Option<&L> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(&self.alphabet[self.codes.get(index) as usize])
+    }
+
+    fn find(&self, from: u64, to: u64, label: &L) -> Option<u64> {
+        let code = self.alphabet.binary_search(label).ok()? as u64;
+        (from..to.min(self.len())).find(|index| self.codes.get(*index) == code)
+    }
+}
+
+/// Repeated labels, stored as bit-packed indices into a dictionary of the distinct labels.
+/// The dictionary is ordered by decreasing frequency and searched linearly, so the labels
+/// only have to be comparable for equality.
+#[derive(Clone, Debug)]
+pub struct DictionaryLabels<L> {
+    dictionary: Vec<L>,
+    codes: PackedInts,
+}
+
+impl<L> SpaceUsage for DictionaryLabels<L> {
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            labels: self.dictionary.len() * mem::size_of::<L>() + self.codes.size_bytes(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+impl<L: Hash + Eq + Clone> LabelStore<L> for DictionaryLabels<L> {
+    fn from_labels(labels: Vec<L>) -> Self {
+        // count the labels, remembering their first occurrence to make the order stable
+        let mut counts: HashMap<&L, (usize, usize)> = HashMap::new();
+        for (index, label) in labels.iter().enumerate() {
+            counts.entry(label).or_insert((0, index)).0 += 1;
+        }
+        let mut entries: Vec<(&L, (usize, usize))> = counts.into_iter().collect();
+        entries.sort_by_key(|&(_, (count, first))| (usize::MAX - count, first));
+        let codes: HashMap<&L, u64> = entries
+            .iter()
+            .enumerate()
+            .map(|(code, &(label, _))| (label, code as u64))
+            .collect();
+        let packed = PackedInts::new(
+            code_width(entries.len()),
+            labels.len() as u64,
+            labels.iter().map(|label| codes[label]),
+        );
+        let dictionary = entries.iter().map(|&(label, _)| label.clone()).collect();
+        Self {
+            dictionary,
+            codes: packed,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.codes.len
+    }
+
+    fn get(&self, index: u64) -> Option<&L> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(&self.dictionary[self.codes.get(index) as usize])
+    }
+
+    fn find(&self, from: u64, to: u64, label: &L) -> Option<u64> {
+        let code = self.dictionary.iter().position(|entry| entry == label)? as u64;
+        (from..to.min(self.len())).find(|index| self.codes.get(*index) == code)
+    }
+}
+
+/// Labels stored as indices into the sorted alphabet in a wavelet matrix: one bit vector per
+/// bit of the codes, from the most significant one. On each level the positions are
+/// reordered stably so that all codes with a 0 come before those with a 1.
+#[derive(Clone)]
+pub struct WaveletLabels<L> {
+    alphabet: Vec<L>,
+    len: u64,
+    levels: Vec<RankSelect>,
+    /// Number of 0s on each level
+    zeros: Vec<u64>,
+}
+
+impl<L> SpaceUsage for WaveletLabels<L> {
+    fn space_usage(&self) -> SpaceBreakdown {
+        let levels: usize = self
+            .levels
+            .iter()
+            .map(|level| level.space_usage().total())
+            .sum();
+        SpaceBreakdown {
+            labels: self.alphabet.len() * mem::size_of::<L>()
+                + levels
+                + self.zeros.len() * mem::size_of::<u64>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+impl<L> WaveletLabels<L> {
+    /// Returns the number of 1s on `level` before `index`.
+    fn rank_1(&self, level: usize, index: u64) -> u64 {
+        if index == 0 {
+            0
+        } else {
+            self.levels[level].rank_1(index - 1).unwrap()
+        }
+    }
+
+    /// Returns the position on the next level of the element at `index` of `level`.
+    fn descend(&self, level: usize, index: u64, bit: bool) -> u64 {
+        if bit {
+            self.zeros[level] + self.rank_1(level, index)
+        } else {
+            index - self.rank_1(level, index)
+        }
+    }
+
+    fn code_bit(&self, code: u64, level: usize) -> bool {
+        code >> (self.levels.len() - 1 - level) & 1 == 1
+    }
+
+    /// Returns the code at `index`.
+    fn access(&self, mut index: u64) -> u64 {
+        let mut code = 0;
+        for level in 0..self.levels.len() {
+            let bit = self.levels[level].get(index);
+            code = code << 1 | u64::from(bit);
+            index = self.descend(level, index, bit);
+        }
+        code
+    }
+
+    /// Returns how often `code` occurs before `index`.
+    fn rank(&self, code: u64, index: u64) -> u64 {
+        let (mut start, mut end) = (0, index);
+        for level in 0..self.levels.len() {
+            let bit = self.code_bit(code, level);
+            start = self.descend(level, start, bit);
+            end = self.descend(level, end, bit);
+        }
+        end - start
+    }
+
+    /// Returns the position of the `rank`-th occurrence of `code`, starting with 1. There
+    /// have to be at least `rank` occurrences.
+    fn select(&self, code: u64, rank: u64) -> u64 {
+        let mut start = 0;
+        for level in 0..self.levels.len() {
+            start = self.descend(level, start, self.code_bit(code, level));
+        }
+        let mut position = start + rank - 1;
+        for level in (0..self.levels.len()).rev() {
+            position = if self.code_bit(code, level) {
+                self.levels[level]
+                    .select_1(position - self.zeros[level] + 1)
+                    .unwrap()
+            } else {
+                self.levels[level].select_0(position + 1).unwrap()
+            };
+        }
+        position
+    }
+}
+
+impl<L: Ord + Clone> LabelStore<L> for WaveletLabels<L> {
+    fn from_labels(labels: Vec<L>) -> Self {
+        let mut alphabet = labels.clone();
+        alphabet.sort();
+        alphabet.dedup();
+        let width = code_width(alphabet.len());
+        let mut codes: Vec<u64> = labels
+            .iter()
+            .map(|label| alphabet.binary_search(label).unwrap() as u64)
+            .collect();
+        let mut levels = Vec::with_capacity(width as usize);
+        let mut zeros = Vec::with_capacity(width as usize);
+        for level in 0..width {
+            let shift = width - 1 - level;
+            let mut bits = BitVec::with_capacity(codes.len() as u64);
+            for code in &codes {
+                bits.push(code >> shift & 1 == 1);
+            }
+            let (mut next, ones): (Vec<u64>, Vec<u64>) =
+                codes.iter().partition(|code| *code >> shift & 1 == 0);
+            zeros.push(next.len() as u64);
+            next.extend(ones);
+            codes = next;
+            levels.push(RankSelect::new(&bits));
+        }
+        Self {
+            alphabet,
+            len: labels.len() as u64,
+            levels,
+            zeros,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn get(&self, index: u64) -> Option<&L> {
+        if index >= self.len {
+            return None;
+        }
+        Some(&self.alphabet[self.access(index) as usize])
+    }
+
+    /// Uses rank and select on the wavelet matrix instead of comparing the labels in the range.
+    fn find(&self, from: u64, to: u64, label: &L) -> Option<u64> {
+        let code = self.alphabet.binary_search(label).ok()? as u64;
+        let to = to.min(self.len);
+        if from >= to {
+            return None;
+        }
+        let before = self.rank(code, from);
+        if before == self.rank(code, to) {
+            return None;
+        }
+        Some(self.select(code, before + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Compares every operation of the store with a plain vector.
+    fn check_store<S: LabelStore<u32>>(labels: &[u32], from: u64, to: u64, label: u32) {
+        let store = S::from_labels(labels.to_vec());
+        assert_eq!(store.len(), labels.len() as u64);
+        assert_eq!(store.to_vec(), labels);
+        assert_eq!(store.get(labels.len() as u64), None);
+        assert_eq!(
+            store.find(from, to, &label),
+            labels.to_vec().find(from, to, &label)
+        );
+    }
+
+    #[test]
+    fn packed_ints() {
+        let values = vec![5u64, 0, 7, 3, 1, 6];
+        let packed = PackedInts::new(3, 6, values.clone());
+        assert_eq!(packed.words.len(), 1);
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(packed.get(index as u64), *value);
+        }
+        let wide = PackedInts::new(64, 2, vec![u64::MAX, 1]);
+        assert_eq!(wide.get(0), u64::MAX);
+        assert_eq!(wide.get(1), 1);
+        let crossing = PackedInts::new(40, 3, vec![1 << 39, 3, (1 << 40) - 1]);
+        assert_eq!(crossing.get(1), 3);
+        assert_eq!(crossing.get(2), (1 << 40) - 1);
+        assert_eq!(code_width(0), 0);
+        assert_eq!(code_width(1), 0);
+        assert_eq!(code_width(2), 1);
+        assert_eq!(code_width(5), 3);
+    }
+
+    #[test]
+    fn dictionary_order() {
+        let labels: Vec<String> = ["b", "a", "a", "c", "a", "b"]
+            .iter()
+            .map(|label| label.to_string())
+            .collect();
+        let store = DictionaryLabels::from_labels(labels.clone());
+        assert_eq!(store.dictionary, vec!["a", "b", "c"]);
+        assert_eq!(store.codes.width, 2);
+        assert_eq!(store.to_vec(), labels);
+        assert_eq!(store.find(2, 6, &"b".to_string()), Some(5));
+    }
+
+    #[test]
+    fn wavelet() {
+        let labels = vec![3u32, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let store = WaveletLabels::from_labels(labels.clone());
+        assert_eq!(store.levels.len(), 3);
+        assert_eq!(store.to_vec(), labels);
+        assert_eq!(store.find(0, 11, &5), Some(4));
+        assert_eq!(store.find(5, 11, &5), Some(8));
+        assert_eq!(store.find(9, 11, &5), Some(10));
+        assert_eq!(store.find(5, 8, &5), None);
+        assert_eq!(store.find(0, 11, &7), None);
+        assert_eq!(store.find(3, 3, &1), None);
+        let single = WaveletLabels::from_labels(vec![7u32; 4]);
+        assert!(single.levels.is_empty());
+        assert_eq!(single.find(1, 4, &7), Some(1));
+        assert!(WaveletLabels::<u32>::from_labels(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn space() {
+        let labels: Vec<u64> = (0..1000).map(|index| index % 4).collect();
+        let plain = labels.space_usage().labels;
+        assert_eq!(plain, 8000);
+        assert_eq!(
+            PackedLabels::from_labels(labels.clone())
+                .space_usage()
+                .labels,
+            4 * 8 + 256
+        );
+        assert!(
+            DictionaryLabels::from_labels(labels.clone())
+                .space_usage()
+                .labels
+                < plain / 16
+        );
+        assert!(WaveletLabels::from_labels(labels).space_usage().labels < plain / 8);
+    }
+
+    proptest! {
+        #[test]
+        fn stores(
+            labels in prop::collection::vec(0u32..20, 0..200),
+            from in 0u64..220,
+            to in 0u64..220,
+            label in 0u32..21,
+        ) {
+            check_store::<Vec<u32>>(&labels, from, to, label);
+            check_store::<PackedLabels<u32>>(&labels, from, to, label);
+            check_store::<DictionaryLabels<u32>>(&labels, from, to, label);
+            check_store::<WaveletLabels<u32>>(&labels, from, to, label);
+        }
+    }
+}
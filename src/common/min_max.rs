@@ -20,27 +20,77 @@
 //! let bits = bit_vec![true, false];
 //!        let min_max = MinMax::new(bits, 2);
 //!        assert_eq!(min_max.excess(0).unwrap(), 1);
+//!
+//! let bits = bit_vec![true, false, false, true, false, true, true];
+//! let min_max = MinMax::new(bits, 2);
+//! assert_eq!(min_max.rmq(1, 6).unwrap(), 2);
+//! assert_eq!(min_max.rMq(1, 6).unwrap(), 6);
+//! assert_eq!(min_max.min_count(1, 6).unwrap(), 2);
+//! assert_eq!(min_max.min_select(1, 6, 2).unwrap(), 4);
 //! # }
 //! ```
 
 use bv::BitVec;
+use common::bit_tables::{popcount, select_in_range, ByteExcess, BYTE_EXCESS};
 use common::errors::NodeError;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::storage::{words_from_bitvec, Plain, Storage};
 use std::cmp;
 use std::f64;
+use std::mem;
+
+/// The block size the trees use if none is chosen.
+pub const DEFAULT_BLOCK_SIZE: u64 = 1024;
+
+/// How many bits of the bit vector are covered by one leaf of the heap. Bigger blocks make
+/// the heap smaller, but the bits inside a block are scanned one at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockSize {
+    /// Always use this many bits per block.
+    Fixed(u64),
+    /// Choose the block size from the length of the bit vector: the smallest power of two
+    /// that is at least log²(n), but not smaller than 64 and not bigger than 4096. The heap
+    /// then needs O(n / log n) bits, while a scan inside a block stays polylogarithmic.
+    Auto,
+}
+
+impl BlockSize {
+    /// Returns the number of bits per block for a bit vector of length `bits_len`.
+    pub fn bits_for(self, bits_len: u64) -> u64 {
+        match self {
+            BlockSize::Fixed(block_size) => block_size,
+            BlockSize::Auto => {
+                let log = u64::from(64 - bits_len.leading_zeros());
+                (log * log).next_power_of_two().clamp(64, 4096)
+            }
+        }
+    }
+}
+
+impl Default for BlockSize {
+    fn default() -> Self {
+        BlockSize::Fixed(DEFAULT_BLOCK_SIZE)
+    }
+}
 
 /// A Range-Min-Max data structure
 #[derive(Serialize, Deserialize)]
 pub struct MinMax {
     bits_len: u64,
-    bits: BitVec<u8>,
+    bits: Storage<u64>,
     block_size: u64,
-    heap: Vec<MinMaxNode>,
+    heap: Storage<MinMaxNode>,
 }
 
 impl MinMax {
     pub fn new(bits: BitVec<u8>, block_size: u64) -> Self {
-        let bits_len = bits.len();
+        Self::from_words(bits.len(), words_from_bitvec(&bits).into(), block_size)
+    }
 
+    /// Creates the MinMax structure for a bit vector of length `bits_len` that is already
+    /// packed into 64 bit words, e.g. the words of a `RankSelect` or a memory-mapped file.
+    /// The words are shared, not copied.
+    pub(crate) fn from_words(bits_len: u64, bits: Storage<u64>, block_size: u64) -> Self {
         let number_of_blocks = if bits_len % block_size != 0 {
             bits_len / block_size + 1
         } else {
@@ -68,7 +118,7 @@ impl MinMax {
             //check if this is a new block:
             if number_min_excess == 0 {
                 begin_of_block = bit_index;
-                if bits[bit_index] {
+                if word_bit(&bits, bit_index) {
                     //initialize the values for the first bit of a block
                     excess = 1;
                     min_excess = 1;
@@ -81,7 +131,7 @@ impl MinMax {
                     max_excess = -1;
                 }
             } else {
-                if !bits[bit_index] {
+                if !word_bit(&bits, bit_index) {
                     //change the excess depending on the bit
                     excess -= 1;
                     if excess == min_excess {
@@ -180,10 +230,60 @@ impl MinMax {
             bits_len,
             bits,
             block_size,
-            heap,
+            heap: heap.into(),
         }
     }
 
+    /// Creates the MinMax structure from a previously built heap.
+    /// Returns `None` if the parts do not belong to a bit vector of length `bits_len`.
+    pub(crate) fn from_parts(
+        bits_len: u64,
+        bits: Storage<u64>,
+        block_size: u64,
+        heap: Storage<MinMaxNode>,
+    ) -> Option<Self> {
+        if block_size == 0 || bits.len() as u64 != bits_len.div_ceil(64) {
+            return None;
+        }
+        let number_of_blocks = bits_len.div_ceil(block_size);
+        let max_blocks = number_of_blocks.max(1).next_power_of_two();
+        if heap.len() as u64 != max_blocks * 2 - 1 {
+            return None;
+        }
+        Some(Self {
+            bits_len,
+            bits,
+            block_size,
+            heap,
+        })
+    }
+
+    /// Returns the number of bits per block.
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Returns the bits packed into 64 bit words.
+    pub fn words(&self) -> &Storage<u64> {
+        &self.bits
+    }
+
+    /// Returns the nodes of the heap.
+    pub fn heap(&self) -> &Storage<MinMaxNode> {
+        &self.heap
+    }
+
+    /// Returns the number of bytes used by the bits and the heap, including storage that is
+    /// mapped from a file.
+    pub fn heap_size_bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.space_usage().total()
+    }
+
+    /// Returns the bit at `index`.
+    fn bit(&self, index: u64) -> bool {
+        word_bit(&self.bits, index)
+    }
+
     fn parent(&self, index: usize) -> usize {
         (index - 1) / 2
     }
@@ -204,13 +304,17 @@ impl MinMax {
     }
 
     pub fn excess(&self, index: u64) -> Result<u64, NodeError> {
-        if index >= self.bits.len() {
+        if index >= self.bits_len {
             return Err(NodeError::NotANodeError);
         }
+        Ok(self.signed_excess(index) as u64)
+    }
+
+    /// Returns the excess at `index`, which is negative for unbalanced bits.
+    fn signed_excess(&self, index: u64) -> i64 {
         let block_number = (index / self.block_size);
         let position_in_block = index % self.block_size;
         let mut pre_excess: i64 = 0;
-        let mut block_excess: i64 = 0;
         let mut heap_number = block_number + (self.heap.len() as u64 / 2);
         while heap_number > 0 {
             if (heap_number % 2) == 0 {
@@ -220,234 +324,436 @@ impl MinMax {
                 heap_number = (heap_number - 1) / 2;
             }
         }
-        for k in (block_number * self.block_size)..=index {
-            if self.bits[k] {
-                block_excess += 1;
-            } else {
-                block_excess -= 1;
-            }
-        }
-        Ok((pre_excess + block_excess) as u64)
+        let block_excess = self.range_excess(block_number * self.block_size, index + 1);
+        pre_excess + block_excess
     }
 
-    fn fwd_search(&self, index: u64, diff: i64) -> Result<u64, NodeError> {
-        let end_of_block = (index / self.block_size) * self.block_size + self.block_size;
-        let index_excess = self.excess(index);
-        let mut current_excess = 0;
-        let mut position_in_block = index;
+    /// Forward search: returns the first position `j > index` with
+    /// `excess(j) = excess(index) + diff`. `diff` may be any positive or negative number and
+    /// the bits do not have to be balanced. The blocks in between are skipped using the heap.
+    /// # Arguments
+    /// * `index` The position to start the search after
+    /// * `diff` The difference of the excess at the result and at `index`
+    ///
+    /// Returns `None` if `index` is out of bounds or if there is no such position.
+    pub fn fwd_search(&self, index: u64, diff: i64) -> Option<u64> {
+        if index >= self.bits_len {
+            return None;
+        }
+        // search the rest of the block of `index`
+        let block = index / self.block_size;
+        let end_of_block = cmp::min((block + 1) * self.block_size, self.bits_len);
+        let excess = match self.scan_first(index + 1, end_of_block, 0, diff) {
+            Ok(position) => return Some(position),
+            Err(excess) => excess,
+        };
 
-        let mut found = false;
-        let mut bottom_up_search = false;
-        let mut top_down_search = false;
-        let mut block_search = false;
-        while !found && position_in_block < end_of_block - 1 {
-            position_in_block += 1;
-            if self.bits[position_in_block] {
-                current_excess += 1;
-            } else {
-                current_excess -= 1;
+        // go up until a right sibling contains the target excess
+        let first_leaf = self.heap.len() / 2;
+        let mut node = first_leaf + block as usize;
+        let mut need = diff - excess;
+        loop {
+            if node == 0 {
+                return None;
             }
-            if current_excess == diff - 1 {
-                found = true;
+            if node % 2 == 1 {
+                let sibling = &self.heap[node + 1];
+                if sibling.bits_for_node > 0
+                    && sibling.min_excess <= need
+                    && need <= sibling.max_excess
+                {
+                    node += 1;
+                    break;
+                }
+                need -= sibling.excess;
             }
+            node = (node - 1) / 2;
         }
-        let mut current_diff = diff - 1 - current_excess;
-        bottom_up_search = true;
-        if (!found) {
-            //bottom up search
-            let mut current_node = (self.heap.len() as u64 / 2 + index / self.block_size) as usize;
-            while bottom_up_search && current_node != 0 {
-                //if current_node is right child go to parent
-                if current_node % 2 == 0 {
-                    current_node = (current_node - 1) / 2;
-                } else {
-                    current_node += 1;
-                    if current_diff <= self.heap[current_node].max_excess
-                        && current_diff >= self.heap[current_node].min_excess
-                    {
-                        bottom_up_search = false;
-                        top_down_search = true;
-                    } else {
-                        //current_diff is not in the right child range. go to parent.
-                        current_diff = current_diff - self.heap[current_node as usize].excess;
-                        current_node = (current_node - 1) / 2;
-                    }
-                }
+
+        // go down to the leftmost block containing the diff excess
+        while node < first_leaf {
+            let left = &self.heap[2 * node + 1];
+            if left.bits_for_node > 0 && left.min_excess <= need && need <= left.max_excess {
+                node = 2 * node + 1;
+            } else {
+                need -= left.excess;
+                node = 2 * node + 2;
             }
-            //top down search
-            while top_down_search {
-                if current_node >= self.heap.len() / 2 {
-                    top_down_search = false;
-                    block_search = true;
-                } else {
-                    let left_child = 2 * current_node + 1;
-                    let right_child = 2 * current_node + 2;
-                    if current_diff <= self.heap[left_child].max_excess
-                        && current_diff >= self.heap[left_child].min_excess
-                    {
-                        current_node = left_child;
-                    } else {
-                        current_node = right_child;
-                        current_diff = current_diff - self.heap[left_child].excess;
-                    }
-                }
+        }
+
+        let start = (node - first_leaf) as u64 * self.block_size;
+        self.scan_first(start, start + self.heap[node].bits_for_node, 0, need)
+            .ok()
+    }
+
+    /// Backward search: returns the last position `j < index` with
+    /// `excess(j) = excess(index) + diff`. `diff` may be any positive or negative number and
+    /// the bits do not have to be balanced. The blocks in between are skipped using the heap.
+    /// # Arguments
+    /// * `index` The position to start the search before
+    /// * `diff` The difference of the excess at the result and at `index`
+    ///
+    /// Returns `None` if `index` is out of bounds or if there is no such position. The
+    /// excess 0 before the first bit is not considered a position, see `find_open`.
+    pub fn bwd_search(&self, index: u64, diff: i64) -> Option<u64> {
+        if index >= self.bits_len {
+            return None;
+        }
+        // search the rest of the block of `index`, `excess` is excess(index) - excess(j)
+        let block = index / self.block_size;
+        let start_of_block = block * self.block_size;
+        let mut excess = self.range_excess(start_of_block, index + 1);
+        if let Some(position) = self.scan_last(start_of_block, index, excess + diff) {
+            return Some(position);
+        }
+
+        // go up until a left sibling contains the target excess
+        let first_leaf = self.heap.len() / 2;
+        let mut node = first_leaf + block as usize;
+        let mut need;
+        loop {
+            if node == 0 {
+                return None;
             }
-            position_in_block = (current_node - self.heap.len() / 2) as u64 * self.block_size;
-            let block_start = position_in_block;
-            let end_of_target_block = block_start + self.block_size;
-            while !found && block_search && position_in_block < end_of_target_block {
-                if self.bits[position_in_block] {
-                    // - and + inverted!
-                    current_diff -= 1;
-                } else {
-                    current_diff += 1;
-                }
-                if current_diff == 0 {
-                    found = true;
-                } else {
-                    position_in_block += 1;
+            if node.is_multiple_of(2) {
+                let sibling = &self.heap[node - 1];
+                need = diff + excess + sibling.excess;
+                if sibling.bits_for_node > 0
+                    && sibling.min_excess <= need
+                    && need <= sibling.max_excess
+                {
+                    node -= 1;
+                    break;
                 }
+                excess += sibling.excess;
+            }
+            node = (node - 1) / 2;
+        }
+
+        // go down to the rightmost block containing the diff excess
+        while node < first_leaf {
+            let left = &self.heap[2 * node + 1];
+            let right = &self.heap[2 * node + 2];
+            let right_need = need - left.excess;
+            if right.bits_for_node > 0
+                && right.min_excess <= right_need
+                && right_need <= right.max_excess
+            {
+                need = right_need;
+                node = 2 * node + 2;
+            } else {
+                node = 2 * node + 1;
             }
         }
-        Ok(position_in_block)
+
+        let start = (node - first_leaf) as u64 * self.block_size;
+        self.scan_last(start, start + self.heap[node].bits_for_node, need)
     }
 
-    fn bwd_search(&self, index: u64, diff: i64) -> Result<i64, NodeError> {
-        let mut block_no = index / self.block_size;
-        let mut begin_of_block = block_no * self.block_size;
-        let mut end_of_block = begin_of_block + self.block_size - 1;
-        let mut current_node = (self.heap.len() / 2) as u64 + block_no;
+    /// Returns the position of the closing parenthesis matching the opening one at `index`.
+    /// # Errors
+    /// * `NotANodeError` If `index` is out of bounds or the parenthesis is never closed.
+    pub fn find_close(&self, index: u64) -> Result<u64, NodeError> {
+        self.fwd_search(index, -1).ok_or(NodeError::NotANodeError)
+    }
 
-        let index_excess = self.excess(index).unwrap() as i64;
-        let mut current_excess = index_excess as i64;
+    /// Returns the position of the opening parenthesis of the pair that directly encloses
+    /// the pair opened at `index`.
+    /// # Errors
+    /// * `NotANodeError` If `index` is out of bounds.
+    /// * `HasNoParentError` If the pair is not enclosed by another pair.
+    pub fn enclose(&self, index: u64) -> Result<u64, NodeError> {
+        self.search_open(index, -2)?
+            .ok_or(NodeError::HasNoParentError)
+    }
 
-        let mut position = index;
-        let mut found = false;
+    /// Returns the position of the opening parenthesis matching the closing one at `index`.
+    /// Like `find_close` it is a backward search that skips blocks using the heap.
+    /// # Errors
+    /// * `NotANodeError` If `index` is out of bounds or the parenthesis is never opened.
+    pub fn find_open(&self, index: u64) -> Result<u64, NodeError> {
+        self.search_open(index, 0)?.ok_or(NodeError::NotANodeError)
+    }
 
-        while !found && position > begin_of_block {
-            if self.bits[position] {
-                current_excess -= 1;
-            } else {
-                current_excess += 1;
-            }
-            position -= 1;
-            if current_excess == index_excess + diff {
-                found = true;
-            }
+    /// Double enclose (also called enclose2): returns the position of the opening
+    /// parenthesis of the innermost pair that contains both pairs opened at `x` and `y`.
+    /// If one of the pairs contains the other, this is the outer pair itself. Otherwise it is
+    /// found with a range minimum query between the pairs and an `enclose`.
+    /// # Arguments
+    /// * `x` The opening parenthesis of the first pair
+    /// * `y` The opening parenthesis of the second pair
+    /// # Errors
+    /// * `NotANodeError` If `x` or `y` is out of bounds or not an opening parenthesis.
+    /// * `HasNoParentError` If no pair contains both pairs.
+    pub fn double_enclose(&self, x: u64, y: u64) -> Result<u64, NodeError> {
+        let (x, y) = (cmp::min(x, y), cmp::max(x, y));
+        if y >= self.bits_len || !self.bit(x) || !self.bit(y) {
+            return Err(NodeError::NotANodeError);
         }
+        if y <= self.find_close(x)? {
+            return Ok(x);
+        }
+        // the minimum closes the outermost pair in between that contains `x`,
+        // so the pair after it is enclosed by the result as well
+        self.enclose(self.rmq(x, y)? + 1)
+    }
 
-        if !found {
-            let mut look_for = diff + index_excess - current_excess;
-            let mut bottom_up = true;
-            let mut top_down = false;
-            let mut block_search = false;
+    /// Returns the position after the last position `j < index` with
+    /// `excess(j) = excess(index) + diff`, including the position 0 if the excess 0 before
+    /// the first bit is the target.
+    fn search_open(&self, index: u64, diff: i64) -> Result<Option<u64>, NodeError> {
+        if index >= self.bits_len {
+            return Err(NodeError::NotANodeError);
+        }
+        Ok(match self.bwd_search(index, diff) {
+            Some(position) => Some(position + 1),
+            None if self.signed_excess(index) + diff == 0 => Some(0),
+            None => None,
+        })
+    }
 
-            while bottom_up && current_node > 0 {
-                if current_node % 2 == 0 {
-                    if self.heap[current_node as usize - 1].max_excess >= -1 * look_for
-                        && self.heap[current_node as usize - 1].min_excess <= -1 * look_for
-                    {
-                        bottom_up = false;
-                        top_down = true;
-                        current_node -= 1;
-                    } else {
-                        look_for = look_for + self.heap[current_node as usize - 1].excess;
-                        current_node = (current_node - 1) / 2;
+    /// Range minimum query: returns the leftmost position with the minimal excess in
+    /// `[start, end]`. The bits do not have to be balanced.
+    /// # Arguments
+    /// * `start` The first position of the range
+    /// * `end` The last position of the range
+    /// # Errors
+    /// * `NotANodeError` If the range is empty or not inside of the bit vector.
+    pub fn rmq(&self, start: u64, end: u64) -> Result<u64, NodeError> {
+        let (min, _) = self.range_min_max(start, end)?;
+        Ok(self.range_first(start, end, min, |node| node.min_excess))
+    }
+
+    /// Range maximum query: returns the leftmost position with the maximal excess in
+    /// `[start, end]`. The bits do not have to be balanced.
+    /// # Arguments
+    /// * `start` The first position of the range
+    /// * `end` The last position of the range
+    /// # Errors
+    /// * `NotANodeError` If the range is empty or not inside of the bit vector.
+    #[allow(non_snake_case)]
+    pub fn rMq(&self, start: u64, end: u64) -> Result<u64, NodeError> {
+        let (_, max) = self.range_min_max(start, end)?;
+        Ok(self.range_first(start, end, max, |node| node.max_excess))
+    }
+
+    /// Returns how often the minimal excess occurs in `[start, end]`.
+    /// # Arguments
+    /// * `start` The first position of the range
+    /// * `end` The last position of the range
+    /// # Errors
+    /// * `NotANodeError` If the range is empty or not inside of the bit vector.
+    pub fn min_count(&self, start: u64, end: u64) -> Result<u64, NodeError> {
+        let (min, _) = self.range_min_max(start, end)?;
+        let mut count = 0;
+        let mut excess = 0;
+        for segment in self.segments(start, end) {
+            match segment {
+                Segment::Bits(from, to) => {
+                    let (found, after) = self.scan_count(from, to, excess, min);
+                    count += found;
+                    excess = after;
+                }
+                Segment::Node(node) => {
+                    let node = &self.heap[node];
+                    if excess + node.min_excess == min {
+                        count += node.number_min_excess;
                     }
-                } else {
-                    current_node = (current_node - 1) / 2;
+                    excess += node.excess;
                 }
             }
+        }
+        Ok(count)
+    }
 
-            while top_down {
-                if current_node >= self.heap.len() as u64 / 2 {
-                    top_down = false;
-                    block_search = true;
-                } else {
-                    if self.heap[current_node as usize * 2 + 2].max_excess
-                        - self.heap[current_node as usize * 2 + 2].min_excess
-                        >= look_for.abs()
-                    {
-                        current_node = current_node * 2 + 2;
-                    } else if self.heap[current_node as usize * 2 + 1].max_excess
-                        - self.heap[current_node as usize * 2 + 1].min_excess
-                        >= look_for.abs()
-                    {
-                        current_node = current_node * 2 + 1;
-                    } else {
-                        //todo konnte nicht gefunden werden!!
+    /// Returns the position of the `rank`-th occurrence of the minimal excess in
+    /// `[start, end]`, starting with 1.
+    /// # Arguments
+    /// * `start` The first position of the range
+    /// * `end` The last position of the range
+    /// * `rank` The number of the occurrence
+    /// # Errors
+    /// * `NotANodeError` If the range is empty or not inside of the bit vector.
+    /// * `NoSuchNodeError` If `rank` is 0 or greater than `min_count(start, end)`.
+    pub fn min_select(&self, start: u64, end: u64, rank: u64) -> Result<u64, NodeError> {
+        let (min, _) = self.range_min_max(start, end)?;
+        let mut remaining = rank;
+        let mut excess = 0;
+        if remaining == 0 {
+            return Err(NodeError::NoSuchNodeError);
+        }
+        for segment in self.segments(start, end) {
+            match segment {
+                Segment::Bits(from, to) => {
+                    match self.scan_select(from, to, excess, min, &mut remaining) {
+                        Ok(position) => return Ok(position),
+                        Err(after) => excess = after,
+                    }
+                }
+                Segment::Node(node) => {
+                    let heap_node = &self.heap[node];
+                    if excess + heap_node.min_excess == min {
+                        if remaining <= heap_node.number_min_excess {
+                            return Ok(self.select_min_in_node(node, remaining));
+                        }
+                        remaining -= heap_node.number_min_excess;
                     }
+                    excess += heap_node.excess;
                 }
             }
+        }
+        Err(NodeError::NoSuchNodeError)
+    }
 
-            /*while top_down {
-                if current_node <= self.heap.len() as u64 / 2 {
-                    top_down = false;
-                    block_search = true;
-                } else {
-                    if self.heap[current_node as usize * 2 + 2].max_excess >= -1 * look_for
-                        && self.heap[current_node as usize * 2 + 2].min_excess <= -1 * look_for
-                        {
-                            current_node = current_node * 2 + 2;
-                        } else if self.heap[current_node as usize * 2 + 1].max_excess >= -1 * look_for
-                        && self.heap[current_node as usize * 2 + 1].min_excess <= -1 * look_for
-                        {
-                            current_node = current_node * 2 + 1;
-                        } else {
-                        //todo konnte nicht gefunden werden!!
+    /// Returns the minimal and maximal excess in `[start, end]`, relative to the excess
+    /// before `start`.
+    fn range_min_max(&self, start: u64, end: u64) -> Result<(i64, i64), NodeError> {
+        if start > end || end >= self.bits_len {
+            return Err(NodeError::NotANodeError);
+        }
+        let (mut min, mut max) = (i64::MAX, i64::MIN);
+        let mut excess = 0;
+        for segment in self.segments(start, end) {
+            match segment {
+                Segment::Bits(from, to) => {
+                    for chunk in self.chunks(from, to) {
+                        let values = chunk.excess();
+                        min = cmp::min(min, excess + i64::from(values.min));
+                        max = cmp::max(max, excess + i64::from(values.max));
+                        excess += i64::from(values.excess);
                     }
                 }
-            }*/
-
-            if block_search {
-                block_no = current_node - (self.heap.len() / 2) as u64;
-                begin_of_block = block_no * self.block_size;
-                end_of_block = begin_of_block + self.block_size - 1;
-                position = end_of_block;
-                while !found && position >= begin_of_block {
-                    if self.bits[position + 1] {
-                        look_for += 1;
-                    } else {
-                        look_for -= 1;
-                    }
-                    if look_for == 0 {
-                        found = true;
-                    } else {
-                        position -= 1
+                Segment::Node(node) => {
+                    let node = &self.heap[node];
+                    min = cmp::min(min, excess + node.min_excess);
+                    max = cmp::max(max, excess + node.max_excess);
+                    excess += node.excess;
+                }
+            }
+        }
+        Ok((min, max))
+    }
+
+    /// Returns the leftmost position in `[start, end]` with the relative excess `target`,
+    /// which has to be the minimum or maximum of the range selected by `bound`.
+    fn range_first<F: Fn(&MinMaxNode) -> i64>(
+        &self,
+        start: u64,
+        end: u64,
+        target: i64,
+        bound: F,
+    ) -> u64 {
+        let mut excess = 0;
+        for segment in self.segments(start, end) {
+            match segment {
+                Segment::Bits(from, to) => match self.scan_first(from, to, excess, target) {
+                    Ok(position) => return position,
+                    Err(after) => excess = after,
+                },
+                Segment::Node(node) => {
+                    if excess + bound(&self.heap[node]) == target {
+                        return self.first_in_node(node, target - excess);
                     }
+                    excess += self.heap[node].excess;
                 }
             }
         }
-        if found {
-            Ok(position as i64)
-        } else {
-            //todo konnte nicht gefunden werden!!
-            Ok(-1)
+        unreachable!("the target excess is the minimum or maximum of the range")
+    }
+
+    /// Returns the leftmost position inside the heap node with the relative excess `need`.
+    fn first_in_node(&self, mut node: usize, mut need: i64) -> u64 {
+        let first_leaf = self.heap.len() / 2;
+        while node < first_leaf {
+            let left = &self.heap[2 * node + 1];
+            if left.bits_for_node > 0 && left.min_excess <= need && need <= left.max_excess {
+                node = 2 * node + 1;
+            } else {
+                need -= left.excess;
+                node = 2 * node + 2;
+            }
+        }
+        let start = (node - first_leaf) as u64 * self.block_size;
+        match self.scan_first(start, start + self.heap[node].bits_for_node, 0, need) {
+            Ok(position) => position,
+            Err(_) => unreachable!("the heap node contains the excess"),
         }
     }
 
-    pub fn find_close(&self, index: u64) -> Result<u64, NodeError> {
-        self.fwd_search(index, 0)
+    /// Returns the position of the `rank`-th occurrence of the minimal excess inside the
+    /// heap node.
+    fn select_min_in_node(&self, mut node: usize, mut rank: u64) -> u64 {
+        let first_leaf = self.heap.len() / 2;
+        let mut min = self.heap[node].min_excess;
+        while node < first_leaf {
+            let left = &self.heap[2 * node + 1];
+            if left.min_excess == min {
+                if rank <= left.number_min_excess {
+                    node = 2 * node + 1;
+                    continue;
+                }
+                rank -= left.number_min_excess;
+            }
+            min -= left.excess;
+            node = 2 * node + 2;
+        }
+        let start = (node - first_leaf) as u64 * self.block_size;
+        match self.scan_select(
+            start,
+            start + self.heap[node].bits_for_node,
+            0,
+            min,
+            &mut rank,
+        ) {
+            Ok(position) => position,
+            Err(_) => unreachable!("the heap node contains the minimum"),
+        }
     }
 
-    pub fn enclose(&self, index: u64) -> Result<i64, NodeError> {
-        Ok(self.bwd_search(index, 2)? + 1)
+    /// Splits `[start, end]` into the parts of the blocks at its ends and the heap nodes
+    /// covering the blocks in between, from left to right.
+    fn segments(&self, start: u64, end: u64) -> Vec<Segment> {
+        let first_block = start / self.block_size;
+        let last_block = end / self.block_size;
+        if first_block == last_block {
+            return vec![Segment::Bits(start, end + 1)];
+        }
+        let mut segments = vec![Segment::Bits(start, (first_block + 1) * self.block_size)];
+        let mut right = Vec::new();
+        let first_leaf = self.heap.len() / 2;
+        let (mut left_node, mut right_node) = (
+            first_leaf + first_block as usize + 1,
+            first_leaf + last_block as usize - 1,
+        );
+        while left_node <= right_node {
+            if left_node.is_multiple_of(2) {
+                segments.push(Segment::Node(left_node));
+                left_node += 1;
+            }
+            if right_node % 2 == 1 {
+                right.push(Segment::Node(right_node));
+                right_node -= 1;
+            }
+            if left_node > right_node {
+                break;
+            }
+            left_node = (left_node - 1) / 2;
+            right_node = (right_node - 1) / 2;
+        }
+        segments.extend(right.into_iter().rev());
+        segments.push(Segment::Bits(last_block * self.block_size, end + 1));
+        segments
     }
 
     pub fn rank_1(&self, index: u64) -> Result<u64, NodeError> {
-        if index >= self.bits.len() {
+        if index >= self.bits_len {
             Err(NodeError::NotANodeError)
         } else {
             let block_no = (index / self.block_size);
             let begin_of_block = block_no * self.block_size;
-            let mut rank = 0;
 
             // Count 1s in the last block
-            for k in begin_of_block..=index {
-                if self.bits[k] {
-                    rank += 1;
-                }
-            }
+            let mut rank = popcount(&self.bits, begin_of_block, index + 1) as i64;
 
             // TODO: rewrite to use helper functions
             let mut current_node = ((self.heap.len() / 2) as u64 + block_no) as usize;
@@ -466,7 +772,7 @@ impl MinMax {
     }
 
     pub fn rank_0(&self, index: u64) -> Result<u64, NodeError> {
-        if index >= self.bits.len() {
+        if index >= self.bits_len {
             return Err(NodeError::NotANodeError);
         }
         Ok(index - self.rank_1(index).unwrap() + 1)
@@ -483,21 +789,7 @@ impl MinMax {
     fn select_1_recursive(&self, rank: i64, heap_index: usize) -> i64 {
         if self.is_leaf(heap_index) {
             // recursion termination: return index of kth "1" in block for k = rank
-            let block_no = (heap_index - self.heap.len() / 2) as i64;
-            let begin_of_block = block_no * self.block_size as i64;
-            let end_of_block = begin_of_block + self.block_size as i64;
-            let mut remaining_rank = rank;
-            let mut index = begin_of_block;
-            // for-loop ends at begin_of_block + bits_for_node because last block might be underfull
-            for bits_index in
-                begin_of_block..begin_of_block + self.heap[heap_index].bits_for_node as i64
-            {
-                if self.bits[bits_index as u64] && remaining_rank > 0 {
-                    remaining_rank -= 1;
-                    index = bits_index;
-                }
-            }
-            return index;
+            return self.select_in_block(heap_index, rank, true);
         } else {
             let no_of_ones = self.ones_for_node(self.left_child(heap_index));
             if no_of_ones >= rank {
@@ -522,21 +814,7 @@ impl MinMax {
     fn select_0_recursive(&self, rank: i64, heap_index: usize) -> i64 {
         if self.is_leaf(heap_index) {
             // recursion termination: return index of kth "0" in block for k = rank
-            let block_no = (heap_index - self.heap.len() / 2) as i64;
-            let begin_of_block = block_no * self.block_size as i64;
-            let end_of_block = begin_of_block + self.block_size as i64;
-            let mut remaining_rank = rank;
-            let mut index = begin_of_block;
-            // for-loop ends at begin_of_block + bits_for_node because last block might be underfull
-            for bits_index in
-                begin_of_block..begin_of_block + self.heap[heap_index].bits_for_node as i64
-            {
-                if !self.bits[bits_index as u64] && remaining_rank > 0 {
-                    remaining_rank -= 1;
-                    index = bits_index;
-                }
-            }
-            return index;
+            return self.select_in_block(heap_index, rank, false);
         } else {
             let no_of_zeroes = self.heap[self.left_child(heap_index)].bits_for_node as i64
                 - self.ones_for_node(self.left_child(heap_index));
@@ -551,13 +829,217 @@ impl MinMax {
         }
     }
 
+    /// Returns the index of the `rank`-th 1 (or 0) in the block of the heap leaf, or the
+    /// begin of the block if there is no such bit.
+    fn select_in_block(&self, heap_index: usize, rank: i64, bit: bool) -> i64 {
+        let begin_of_block = (heap_index - self.heap.len() / 2) as u64 * self.block_size;
+        // the range ends at begin_of_block + bits_for_node because the last block might be underfull
+        let end_of_block = begin_of_block + self.heap[heap_index].bits_for_node;
+        let ones = popcount(&self.bits, begin_of_block, end_of_block);
+        let matching = if bit {
+            ones
+        } else {
+            end_of_block - begin_of_block - ones
+        };
+        // without enough matching bits, the last one is returned
+        let rank = cmp::min(rank.max(0) as u64, matching);
+        if rank == 0 {
+            return begin_of_block as i64;
+        }
+        select_in_range(&self.bits, begin_of_block, end_of_block, bit, rank).unwrap() as i64
+    }
+
+    /// Returns the excess of the bits `[from, to)`.
+    fn range_excess(&self, from: u64, to: u64) -> i64 {
+        2 * popcount(&self.bits, from, to) as i64 - (to - from) as i64
+    }
+
+    /// Returns the parts of the bits `[from, to)`, whole bytes where possible.
+    fn chunks(&self, from: u64, to: u64) -> Chunks<'_> {
+        Chunks {
+            words: &self.bits,
+            position: from,
+            end: to,
+        }
+    }
+
+    /// Returns the first position in `[from, to)` where the excess reaches `target`, given
+    /// the excess `excess` before `from`. Otherwise returns the excess after `to`.
+    fn scan_first(&self, from: u64, to: u64, mut excess: i64, target: i64) -> Result<u64, i64> {
+        for chunk in self.chunks(from, to) {
+            let values = chunk.excess();
+            if excess + i64::from(values.min) <= target && target <= excess + i64::from(values.max)
+            {
+                for offset in 0..chunk.len {
+                    excess += chunk.step(offset);
+                    if excess == target {
+                        return Ok(chunk.start + offset);
+                    }
+                }
+                unreachable!("the chunk contains the target excess");
+            }
+            excess += i64::from(values.excess);
+        }
+        Err(excess)
+    }
+
+    /// Returns the last position in `[from, to)` where the excess relative to the excess
+    /// before `from` is `target`.
+    fn scan_last(&self, from: u64, to: u64, target: i64) -> Option<u64> {
+        let mut excess = 0;
+        let mut last = None;
+        for chunk in self.chunks(from, to) {
+            let values = chunk.excess();
+            if excess + i64::from(values.min) <= target && target <= excess + i64::from(values.max)
+            {
+                last = Some((chunk, excess));
+            }
+            excess += i64::from(values.excess);
+        }
+        let (chunk, mut excess) = last?;
+        let mut found = None;
+        for offset in 0..chunk.len {
+            excess += chunk.step(offset);
+            if excess == target {
+                found = Some(chunk.start + offset);
+            }
+        }
+        found
+    }
+
+    /// Returns how often the excess is `target` in `[from, to)` and the excess after `to`,
+    /// given the excess `excess` before `from`. `target` must not be above the minimum.
+    fn scan_count(&self, from: u64, to: u64, mut excess: i64, target: i64) -> (u64, i64) {
+        let mut count = 0;
+        for chunk in self.chunks(from, to) {
+            let values = chunk.excess();
+            if excess + i64::from(values.min) == target {
+                count += u64::from(values.min_count);
+            }
+            excess += i64::from(values.excess);
+        }
+        (count, excess)
+    }
+
+    /// Returns the position of the `remaining`-th occurrence of the excess `target` in
+    /// `[from, to)`, given the excess `excess` before `from`. Otherwise returns the excess
+    /// after `to` and decreases `remaining` by the number of occurrences. `target` must not be
+    /// above the minimum.
+    fn scan_select(
+        &self,
+        from: u64,
+        to: u64,
+        mut excess: i64,
+        target: i64,
+        remaining: &mut u64,
+    ) -> Result<u64, i64> {
+        for chunk in self.chunks(from, to) {
+            let values = chunk.excess();
+            if excess + i64::from(values.min) == target {
+                if *remaining <= u64::from(values.min_count) {
+                    for offset in 0..chunk.len {
+                        excess += chunk.step(offset);
+                        if excess == target {
+                            *remaining -= 1;
+                            if *remaining == 0 {
+                                return Ok(chunk.start + offset);
+                            }
+                        }
+                    }
+                }
+                *remaining -= u64::from(values.min_count);
+            }
+            excess += i64::from(values.excess);
+        }
+        Err(excess)
+    }
+
     /// Returns the number of 1s belonging to the heap node
     fn ones_for_node(&self, heap_index: usize) -> i64 {
         ((self.heap[heap_index].bits_for_node as i64 + self.heap[heap_index].excess) / 2)
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+impl SpaceUsage for MinMax {
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            bits: self.bits.len() * mem::size_of::<u64>(),
+            minmax_heap: self.heap.len() * mem::size_of::<MinMaxNode>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+/// A part of a range, either bits inside of a block or a heap node.
+enum Segment {
+    Bits(u64, u64),
+    Node(usize),
+}
+
+/// A part of the bits that is scanned at once: a whole byte or a single bit.
+#[derive(Clone, Copy)]
+struct Chunk {
+    start: u64,
+    len: u64,
+    bits: u8,
+}
+
+impl Chunk {
+    fn excess(&self) -> ByteExcess {
+        if self.len == 8 {
+            BYTE_EXCESS[self.bits as usize]
+        } else {
+            ByteExcess::of_bit(self.bits & 1 == 1)
+        }
+    }
+
+    /// Returns the change of the excess caused by the bit at `offset` of the chunk.
+    fn step(&self, offset: u64) -> i64 {
+        if self.bits >> offset & 1 == 1 {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+/// Iterator over the chunks of a range of bits. Bits are returned one at a time until the
+/// next byte boundary, then whole bytes as long as they lie inside of the range.
+struct Chunks<'a> {
+    words: &'a [u64],
+    position: u64,
+    end: u64,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        if self.position >= self.end {
+            return None;
+        }
+        let word = self.words[(self.position / 64) as usize] >> (self.position % 64);
+        let len = if self.position.is_multiple_of(8) && self.position + 8 <= self.end {
+            8
+        } else {
+            1
+        };
+        let chunk = Chunk {
+            start: self.position,
+            len,
+            bits: word as u8,
+        };
+        self.position += len;
+        Some(chunk)
+    }
+}
+
+fn word_bit(words: &[u64], index: u64) -> bool {
+    words[(index / 64) as usize] >> (index % 64) & 1 == 1
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[repr(C)]
 pub struct MinMaxNode {
     excess: i64,
     min_excess: i64,
@@ -566,6 +1048,27 @@ pub struct MinMaxNode {
     bits_for_node: u64,
 }
 
+unsafe impl Plain for MinMaxNode {
+    fn read_le(bytes: &[u8]) -> Self {
+        let field = |index: usize| u64::read_le(&bytes[8 * index..]);
+        Self {
+            excess: field(0) as i64,
+            min_excess: field(1) as i64,
+            number_min_excess: field(2),
+            max_excess: field(3) as i64,
+            bits_for_node: field(4),
+        }
+    }
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        (self.excess as u64).write_le(out);
+        (self.min_excess as u64).write_le(out);
+        self.number_min_excess.write_le(out);
+        (self.max_excess as u64).write_le(out);
+        self.bits_for_node.write_le(out);
+    }
+}
+
 impl MinMaxNode {
     pub fn set_values(
         &mut self,
@@ -588,6 +1091,29 @@ mod tests {
     use super::*;
     use bv::BitVec;
     use bv::Bits;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_block_size() {
+        assert_eq!(BlockSize::default().bits_for(10), DEFAULT_BLOCK_SIZE);
+        assert_eq!(BlockSize::Fixed(8).bits_for(1 << 40), 8);
+        assert_eq!(BlockSize::Auto.bits_for(0), 64);
+        assert_eq!(BlockSize::Auto.bits_for(1000), 128);
+        assert_eq!(BlockSize::Auto.bits_for(1 << 20), 512);
+        assert_eq!(BlockSize::Auto.bits_for(u64::MAX), 4096);
+    }
+
+    #[test]
+    fn test_heap_size_bytes() {
+        let bits: BitVec<u8> = BitVec::new_fill(true, 4096);
+        let small_blocks = MinMax::new(bits.clone(), 64);
+        let big_blocks = MinMax::new(bits, 1024);
+        assert_eq!(
+            small_blocks.heap_size_bytes(),
+            mem::size_of::<MinMax>() + 64 * 8 + 127 * mem::size_of::<MinMaxNode>()
+        );
+        assert!(big_blocks.heap_size_bytes() < small_blocks.heap_size_bytes());
+    }
 
     #[test]
     fn test_min_max_construction() {
@@ -683,12 +1209,14 @@ mod tests {
         let bits =
             bit_vec![true, true, true, false, true, false, false, true, true, false, false, false];
         let min_max = MinMax::new(bits, 4);
-        assert_eq!(min_max.fwd_search(0, 0).unwrap(), 11);
-        assert_eq!(min_max.fwd_search(1, 0).unwrap(), 6);
-        assert_eq!(min_max.fwd_search(5, 2).unwrap(), 8);
-        assert_eq!(min_max.fwd_search(4, 0).unwrap(), 5);
-        assert_eq!(min_max.fwd_search(4, 1).unwrap(), 8);
-        assert_eq!(min_max.fwd_search(0, 3).unwrap(), 2);
+        assert_eq!(min_max.fwd_search(0, -1), Some(11));
+        assert_eq!(min_max.fwd_search(1, -1), Some(6));
+        assert_eq!(min_max.fwd_search(5, 1), Some(8));
+        assert_eq!(min_max.fwd_search(4, -1), Some(5));
+        assert_eq!(min_max.fwd_search(4, 0), Some(8));
+        assert_eq!(min_max.fwd_search(0, 2), Some(2));
+        assert_eq!(min_max.fwd_search(0, -2), None);
+        assert_eq!(min_max.fwd_search(12, 0), None);
     }
 
     #[test]
@@ -704,13 +1232,14 @@ mod tests {
         let bits =
             bit_vec![true, true, true, false, true, false, false, true, true, false, false, false];
         let min_max = MinMax::new(bits, 4);
-        assert_eq!(min_max.bwd_search(7, 1).unwrap(), 4);
-        assert_eq!(min_max.bwd_search(5, -1).unwrap(), 0);
-        assert_eq!(min_max.bwd_search(10, 0).unwrap(), 6);
+        assert_eq!(min_max.bwd_search(7, 1), Some(4));
+        assert_eq!(min_max.bwd_search(5, -1), Some(0));
+        assert_eq!(min_max.bwd_search(10, 0), Some(6));
+        assert_eq!(min_max.bwd_search(11, 0), None);
+        assert_eq!(min_max.bwd_search(12, 0), None);
     }
 
     #[test]
-    #[ignore]
     fn test_enclose() {
         let bits = bit_vec![
             true, true, true, false, true, false, true, true, false, false, false, true, false,
@@ -719,6 +1248,48 @@ mod tests {
         let min_max = MinMax::new(bits, 4);
         assert_eq!(min_max.enclose(4).unwrap(), 1);
         assert_eq!(min_max.enclose(6).unwrap(), 1);
+        assert_eq!(min_max.enclose(1).unwrap(), 0);
+        assert_eq!(min_max.enclose(0).unwrap_err(), NodeError::HasNoParentError);
+    }
+
+    #[test]
+    fn test_double_enclose() {
+        let bits = bit_vec![
+            true, true, true, false, true, false, true, true, false, false, false, true, false,
+            true, true, true, false, true, false, false, false, false
+        ];
+        let min_max = MinMax::new(bits, 4);
+        assert_eq!(min_max.double_enclose(2, 4).unwrap(), 1);
+        assert_eq!(min_max.double_enclose(7, 2).unwrap(), 1);
+        assert_eq!(min_max.double_enclose(7, 15).unwrap(), 0);
+        assert_eq!(min_max.double_enclose(15, 17).unwrap(), 14);
+        assert_eq!(min_max.double_enclose(1, 7).unwrap(), 1);
+        assert_eq!(min_max.double_enclose(0, 0).unwrap(), 0);
+        assert_eq!(
+            min_max.double_enclose(4, 8).unwrap_err(),
+            NodeError::NotANodeError
+        );
+        let min_max = MinMax::new(bit_vec![true, false, true, false], 2);
+        assert_eq!(
+            min_max.double_enclose(0, 2).unwrap_err(),
+            NodeError::HasNoParentError
+        );
+    }
+
+    #[test]
+    fn test_find_open() {
+        let bits = bit_vec![
+            true, true, true, false, true, false, true, true, false, false, false, true, false,
+            true, true, true, false, true, false, false, false, false
+        ];
+        let min_max = MinMax::new(bits, 4);
+        assert_eq!(min_max.find_open(3).unwrap(), 2);
+        assert_eq!(min_max.find_open(10).unwrap(), 1);
+        assert_eq!(min_max.find_open(20).unwrap(), 13);
+        assert_eq!(min_max.find_open(21).unwrap(), 0);
+        assert_eq!(min_max.find_open(22).unwrap_err(), NodeError::NotANodeError);
+        let min_max = MinMax::new(bit_vec![false, true], 2);
+        assert_eq!(min_max.find_open(0).unwrap_err(), NodeError::NotANodeError);
     }
 
     #[test]
@@ -817,4 +1388,160 @@ mod tests {
         assert_eq!(min_max.select_0(12).unwrap_err(), NodeError::NotANodeError);
     }
 
+    #[test]
+    fn test_range_queries() {
+        let bits = bit_vec![
+            true, true, true, false, true, false, true, true, false, false, false, true, false,
+            true, true, true, false, true, false, false, false, false
+        ];
+        let min_max = MinMax::new(bits, 4);
+        assert_eq!(min_max.rmq(3, 12).unwrap(), 10);
+        assert_eq!(min_max.rMq(3, 12).unwrap(), 7);
+        assert_eq!(min_max.min_count(3, 12).unwrap(), 2);
+        assert_eq!(min_max.min_select(3, 12, 2).unwrap(), 12);
+        assert_eq!(min_max.min_count(1, 20).unwrap(), 3);
+        assert_eq!(
+            min_max.min_select(1, 20, 4).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        assert_eq!(min_max.rmq(5, 4).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(min_max.rmq(5, 22).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    proptest! {
+        #[test]
+        fn range_queries_oracle(
+            bits in prop::collection::vec(any::<bool>(), 1..80),
+            block_size in 1u64..12,
+        ) {
+            let mut bitvec = BitVec::new();
+            let mut excess = Vec::with_capacity(bits.len());
+            let mut current = 0i64;
+            for bit in &bits {
+                bitvec.push(*bit);
+                current += if *bit { 1 } else { -1 };
+                excess.push(current);
+            }
+            let min_max = MinMax::new(bitvec, block_size);
+            let len = bits.len() as u64;
+            for start in 0..len {
+                let mut minima: Vec<u64> = Vec::new();
+                let mut max = start;
+                for end in start..len {
+                    let value = excess[end as usize];
+                    if minima.is_empty() || value < excess[minima[0] as usize] {
+                        minima = vec![end];
+                    } else if value == excess[minima[0] as usize] {
+                        minima.push(end);
+                    }
+                    if value > excess[max as usize] {
+                        max = end;
+                    }
+                    prop_assert_eq!(min_max.rmq(start, end).unwrap(), minima[0]);
+                    prop_assert_eq!(min_max.rMq(start, end).unwrap(), max);
+                    prop_assert_eq!(min_max.min_count(start, end).unwrap(), minima.len() as u64);
+                    let last = minima.len() as u64;
+                    for rank in &[1, last.div_ceil(2), last] {
+                        prop_assert_eq!(
+                            min_max.min_select(start, end, *rank).unwrap(),
+                            minima[*rank as usize - 1]
+                        );
+                    }
+                    prop_assert!(min_max.min_select(start, end, last + 1).is_err());
+                }
+            }
+        }
+    }
+
+    /// Random bits that are balanced parentheses in about half of the cases.
+    fn arb_bits() -> impl Strategy<Value = Vec<bool>> {
+        (prop::collection::vec(any::<bool>(), 1..120), any::<bool>()).prop_map(
+            |(bits, balanced)| {
+                if !balanced {
+                    return bits;
+                }
+                let mut balanced_bits = Vec::with_capacity(2 * bits.len());
+                let mut excess = 0;
+                for bit in bits {
+                    if bit || excess == 0 {
+                        balanced_bits.push(true);
+                        excess += 1;
+                    } else {
+                        balanced_bits.push(false);
+                        excess -= 1;
+                    }
+                }
+                balanced_bits.extend((0..excess).map(|_| false));
+                balanced_bits
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn searches_oracle(bits in arb_bits(), block_size in 1u64..12) {
+            let mut bitvec = BitVec::new();
+            let mut excess = Vec::with_capacity(bits.len());
+            let mut current = 0i64;
+            for bit in &bits {
+                bitvec.push(*bit);
+                current += if *bit { 1 } else { -1 };
+                excess.push(current);
+            }
+            let len = bits.len() as u64;
+            let min_max = MinMax::new(bitvec, block_size);
+            for index in 0..len {
+                for diff in -5..6 {
+                    let target = excess[index as usize] + diff;
+                    let forward = (index + 1..len).find(|j| excess[*j as usize] == target);
+                    let backward = (0..index).rev().find(|j| excess[*j as usize] == target);
+                    prop_assert_eq!(min_max.fwd_search(index, diff), forward);
+                    prop_assert_eq!(min_max.bwd_search(index, diff), backward);
+                }
+            }
+            prop_assert_eq!(min_max.fwd_search(len, 0), None);
+            prop_assert_eq!(min_max.bwd_search(len, 0), None);
+        }
+
+        #[test]
+        fn block_scans_oracle(
+            bits in prop::collection::vec(any::<bool>(), 1..700),
+            block_size in 8u64..300,
+            start in any::<prop::sample::Index>(),
+        ) {
+            let mut bitvec = BitVec::new();
+            let mut excess = Vec::with_capacity(bits.len());
+            let mut current = 0i64;
+            for bit in &bits {
+                bitvec.push(*bit);
+                current += if *bit { 1 } else { -1 };
+                excess.push(current);
+            }
+            let len = bits.len() as u64;
+            let min_max = MinMax::new(bitvec, block_size);
+            let ones: Vec<u64> = (0..len).filter(|i| bits[*i as usize]).collect();
+            for index in 0..len {
+                prop_assert_eq!(min_max.signed_excess(index), excess[index as usize]);
+                let rank = ones.iter().filter(|j| **j <= index).count() as u64;
+                prop_assert_eq!(min_max.rank_1(index).unwrap(), rank);
+                let target = excess[index as usize] - 1;
+                let forward = (index + 1..len).find(|j| excess[*j as usize] == target);
+                let backward = (0..index).rev().find(|j| excess[*j as usize] == target);
+                prop_assert_eq!(min_max.fwd_search(index, -1), forward);
+                prop_assert_eq!(min_max.bwd_search(index, -1), backward);
+            }
+            for (rank, position) in ones.iter().enumerate().take(len as usize / 2) {
+                prop_assert_eq!(min_max.select_1(rank as u64 + 1).unwrap(), *position);
+            }
+            let start = start.index(bits.len()) as u64;
+            let range = &excess[start as usize..];
+            let min = *range.iter().min().unwrap();
+            let count = range.iter().filter(|e| **e == min).count() as u64;
+            prop_assert_eq!(min_max.min_count(start, len - 1).unwrap(), count);
+            let first = range.iter().position(|e| *e == min).unwrap() as u64 + start;
+            prop_assert_eq!(min_max.rmq(start, len - 1).unwrap(), first);
+            let last = range.iter().rposition(|e| *e == min).unwrap() as u64 + start;
+            prop_assert_eq!(min_max.min_select(start, len - 1, count).unwrap(), last);
+        }
+    }
 }
@@ -1,3 +1,13 @@
+pub mod bit_tables;
+pub mod container;
 pub mod errors;
+pub mod label_store;
 pub mod min_max;
+pub mod parentheses;
+pub mod rank_select;
+pub mod space_usage;
+pub mod storage;
 pub mod succinct_tree;
+#[cfg(test)]
+pub mod test_util;
+pub mod traversal;
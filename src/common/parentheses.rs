@@ -0,0 +1,213 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Streaming reader for balanced parenthesis sequences, used by `from_reader` of the trees.
+//!
+//! The input is either text of `(` and `)` characters or bits packed into bytes, the first
+//! bit being the least significant bit of the first byte. The sequence is validated while it
+//! is read: it has to start with an opening parenthesis and ends as soon as all parentheses
+//! are closed.
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::common::parentheses::{read_bits, InputFormat};
+//!
+//! let bits = read_bits("(()())\n".as_bytes(), InputFormat::Parentheses).unwrap();
+//! assert_eq!(bits, bit_vec![true, true, false, true, false, false]);
+//! # }
+//! ```
+
+use bv::BitVec;
+use common::errors::FileError;
+use std::io::{BufReader, Read};
+
+/// The encoding of a parenthesis sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Text of `(` and `)` characters. ASCII whitespace is ignored.
+    Parentheses,
+    /// One bit per parenthesis, 1 for `(`. The last byte is padded with 0 bits.
+    PackedBits,
+}
+
+/// Reads a balanced parenthesis sequence.
+/// # Arguments
+/// * `reader` The input to read from
+/// * `format` The encoding of the input
+/// # Errors
+/// * `IoError` If the input cannot be read.
+/// * `InvalidCharacterError` If the text contains a character other than `(`, `)` or whitespace.
+/// * `UnmatchedCloseError` If the sequence starts with a closing parenthesis.
+/// * `UnexpectedEndError` If the input ends before all parentheses are closed.
+/// * `TrailingDataError` If there is further data after the last parenthesis has been closed.
+///
+/// The offsets of all errors are byte offsets into the input.
+pub fn read_bits<R: Read>(reader: R, format: InputFormat) -> Result<BitVec<u8>, FileError> {
+    let mut sequence = Sequence::new();
+    let mut offset = 0;
+    for byte in BufReader::new(reader).bytes() {
+        let byte = byte?;
+        match format {
+            InputFormat::Parentheses => match byte {
+                b'(' => sequence.push(true, offset)?,
+                b')' => sequence.push(false, offset)?,
+                _ if byte.is_ascii_whitespace() => {}
+                _ => {
+                    return Err(FileError::InvalidCharacterError {
+                        offset,
+                        character: char::from(byte),
+                    })
+                }
+            },
+            InputFormat::PackedBits => {
+                if sequence.is_closed() {
+                    return Err(FileError::TrailingDataError(offset));
+                }
+                for bit in 0..8 {
+                    let value = byte >> bit & 1 == 1;
+                    if sequence.is_closed() && !value {
+                        // padding of the last byte
+                        continue;
+                    }
+                    sequence.push(value, offset)?;
+                }
+            }
+        }
+        offset += 1;
+    }
+    if sequence.is_closed() {
+        Ok(sequence.bits)
+    } else {
+        Err(FileError::UnexpectedEndError(offset))
+    }
+}
+
+/// A parenthesis sequence that is validated while it is built.
+struct Sequence {
+    bits: BitVec<u8>,
+    excess: u64,
+}
+
+impl Sequence {
+    fn new() -> Self {
+        Self {
+            bits: BitVec::new(),
+            excess: 0,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.excess == 0 && !self.bits.is_empty()
+    }
+
+    fn push(&mut self, bit: bool, offset: u64) -> Result<(), FileError> {
+        if self.is_closed() {
+            return Err(FileError::TrailingDataError(offset));
+        }
+        if bit {
+            self.excess += 1;
+        } else if self.excess == 0 {
+            return Err(FileError::UnmatchedCloseError(offset));
+        } else {
+            self.excess -= 1;
+        }
+        self.bits.push(bit);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<BitVec<u8>, FileError> {
+        read_bits(input.as_bytes(), InputFormat::Parentheses)
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(
+            parse("(()(()))").unwrap(),
+            bit_vec![true, true, false, true, true, false, false, false]
+        );
+        assert_eq!(parse(" (\n) \r\n").unwrap(), bit_vec![true, false]);
+    }
+
+    #[test]
+    fn invalid_character() {
+        match parse("(()x)") {
+            Err(FileError::InvalidCharacterError {
+                offset: 3,
+                character: 'x',
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unmatched_close() {
+        match parse("  )()") {
+            Err(FileError::UnmatchedCloseError(2)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unexpected_end() {
+        match parse("(()") {
+            Err(FileError::UnexpectedEndError(3)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match parse("") {
+            Err(FileError::UnexpectedEndError(0)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trailing_data() {
+        match parse("(())()") {
+            Err(FileError::TrailingDataError(4)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn packed_bits() {
+        let bits = read_bits(&[0b0000_1011][..], InputFormat::PackedBits).unwrap();
+        assert_eq!(bits, bit_vec![true, true, false, true, false, false]);
+        let bits = read_bits(&[0b0001_1111, 0b0000_0000][..], InputFormat::PackedBits).unwrap();
+        assert_eq!(
+            bits,
+            bit_vec![true, true, true, true, true, false, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn packed_bits_errors() {
+        match read_bits(&[0b1111_1111][..], InputFormat::PackedBits) {
+            Err(FileError::UnexpectedEndError(1)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match read_bits(&[0b0100_0001][..], InputFormat::PackedBits) {
+            Err(FileError::TrailingDataError(0)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match read_bits(&[0b0000_1011, 0][..], InputFormat::PackedBits) {
+            Err(FileError::TrailingDataError(1)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match read_bits(&[0b0000_0010][..], InputFormat::PackedBits) {
+            Err(FileError::UnmatchedCloseError(0)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rank/Select data structure over a bit vector stored in 64 bit words. It uses a single
+//! directory of superblocks for rank and binary searches this directory for select.
+//! In contrast to the Rust-Bio implementation it can be backed by a memory-mapped file.
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::common::rank_select::RankSelect;
+//!
+//! let rankselect = RankSelect::new(&bit_vec![true, false, true, true]);
+//! assert_eq!(rankselect.rank_1(2), Some(2));
+//! assert_eq!(rankselect.select_0(1), Some(1));
+//! # }
+//! ```
+
+use bv::{BitSlice, BitSliceable, BitVec};
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::storage::{bitvec_from_words, words_from_bitvec, Storage};
+use std::mem;
+
+/// Number of 64 bit words per superblock of the rank directory.
+pub const SUPERBLOCK_WORDS: usize = 8;
+
+/// A Rank/Select data structure
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RankSelect {
+    len: u64,
+    words: Storage<u64>,
+    /// Number of 1s before each superblock, followed by the total number of 1s.
+    superblocks: Storage<u64>,
+}
+
+impl RankSelect {
+    /// Creates the rank/select structure for a bit vector.
+    pub fn new(bits: &BitVec<u8>) -> Self {
+        let words = words_from_bitvec(bits);
+        let superblocks = Self::build_superblocks(&words);
+        Self {
+            len: bits.len(),
+            words: words.into(),
+            superblocks: superblocks.into(),
+        }
+    }
+
+    /// Creates the rank/select structure from previously built parts.
+    /// Returns `None` if the parts do not belong to a bit vector of length `len`.
+    pub(crate) fn from_parts(
+        len: u64,
+        words: Storage<u64>,
+        superblocks: Storage<u64>,
+    ) -> Option<Self> {
+        let word_count = len.div_ceil(64) as usize;
+        if words.len() != word_count
+            || superblocks.len() != word_count.div_ceil(SUPERBLOCK_WORDS) + 1
+        {
+            return None;
+        }
+        Some(Self {
+            len,
+            words,
+            superblocks,
+        })
+    }
+
+    /// Creates the rank directory for already packed words.
+    pub(crate) fn build_superblocks(words: &[u64]) -> Vec<u64> {
+        let mut superblocks = Vec::with_capacity(words.len() / SUPERBLOCK_WORDS + 2);
+        let mut ones = 0;
+        for chunk in words.chunks(SUPERBLOCK_WORDS) {
+            superblocks.push(ones);
+            ones += chunk
+                .iter()
+                .map(|word| u64::from(word.count_ones()))
+                .sum::<u64>();
+        }
+        superblocks.push(ones);
+        superblocks
+    }
+
+    /// Returns the length of the bit vector.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the bit vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bit vector.
+    pub fn bits(&self) -> BitSlice<'_, u64> {
+        BitSlice::from_slice(&self.words).bit_slice(..self.len)
+    }
+
+    /// Returns a copy of the bit vector.
+    pub fn to_bitvec(&self) -> BitVec<u8> {
+        bitvec_from_words(&self.words, self.len)
+    }
+
+    /// Returns the bit vector packed into 64 bit words.
+    pub fn words(&self) -> &Storage<u64> {
+        &self.words
+    }
+
+    /// Returns the rank directory.
+    pub fn superblocks(&self) -> &Storage<u64> {
+        &self.superblocks
+    }
+
+    /// Returns the bit at `index`.
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn get(&self, index: u64) -> bool {
+        assert!(index < self.len, "RankSelect::get: out of bounds");
+        self.words[(index / 64) as usize] >> (index % 64) & 1 == 1
+    }
+
+    /// Returns the number of 1s up to `index` (inclusive) or `None` if `index` is out of bounds.
+    pub fn rank_1(&self, index: u64) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        let word = (index / 64) as usize;
+        let superblock = word / SUPERBLOCK_WORDS;
+        let mut rank = self.superblocks[superblock];
+        for w in superblock * SUPERBLOCK_WORDS..word {
+            rank += u64::from(self.words[w].count_ones());
+        }
+        let offset = index % 64;
+        let mask = if offset == 63 {
+            !0
+        } else {
+            (1 << (offset + 1)) - 1
+        };
+        Some(rank + u64::from((self.words[word] & mask).count_ones()))
+    }
+
+    /// Returns the number of 0s up to `index` (inclusive) or `None` if `index` is out of bounds.
+    pub fn rank_0(&self, index: u64) -> Option<u64> {
+        self.rank_1(index).map(|rank| index + 1 - rank)
+    }
+
+    /// Returns the position of the `rank`-th 1 or `None` if there is no such 1.
+    pub fn select_1(&self, rank: u64) -> Option<u64> {
+        self.select(rank, true)
+    }
+
+    /// Returns the position of the `rank`-th 0 or `None` if there is no such 0.
+    pub fn select_0(&self, rank: u64) -> Option<u64> {
+        self.select(rank, false)
+    }
+
+    fn select(&self, rank: u64, bit: bool) -> Option<u64> {
+        let superblock_bits = (SUPERBLOCK_WORDS * 64) as u64;
+        let last = self.superblocks.len() - 1;
+        let count = |superblock: usize| {
+            if bit {
+                self.superblocks[superblock]
+            } else if superblock == last {
+                self.len - self.superblocks[last]
+            } else {
+                superblock as u64 * superblock_bits - self.superblocks[superblock]
+            }
+        };
+        let word = |word_index: usize| {
+            if bit {
+                self.words[word_index]
+            } else {
+                !self.words[word_index]
+            }
+        };
+        select_with(&count, last + 1, &word, self.words.len(), rank)
+    }
+
+    /// Returns the word at `word_index` with a 1 at every position that starts the pattern
+    /// `10`. A 1 in the last bit is not followed by a 0 and does not start the pattern.
+    fn pattern_10_word(&self, word_index: usize) -> u64 {
+        let word = self.words[word_index];
+        let next = if word_index + 1 < self.words.len() {
+            self.words[word_index + 1]
+        } else if self.len.is_multiple_of(64) {
+            // the last bit is the most significant bit of the word
+            1
+        } else {
+            // unused bits are zero, so the last bit is masked explicitly
+            return word & !(word >> 1) & !(1 << (self.len % 64 - 1));
+        };
+        word & !(word >> 1 | next << 63)
+    }
+}
+
+/// Rank/select directory for the occurrences of the pattern `10` in the bits of a
+/// `RankSelect`, e.g. the leaves of a BP tree. It only stores the number of occurrences
+/// before every superblock and reads the bits from the `RankSelect` it has been built for,
+/// so it needs one word per superblock.
+#[derive(Clone, Debug)]
+pub struct PatternRankSelect {
+    /// Number of occurrences before each superblock, followed by the total number.
+    superblocks: Vec<u64>,
+}
+
+impl PatternRankSelect {
+    /// Creates the directory for the bits of `rankselect`.
+    pub fn new(rankselect: &RankSelect) -> Self {
+        let mut superblocks = Vec::with_capacity(rankselect.words.len() / SUPERBLOCK_WORDS + 2);
+        let mut count = 0;
+        for word_index in 0..rankselect.words.len() {
+            if word_index % SUPERBLOCK_WORDS == 0 {
+                superblocks.push(count);
+            }
+            count += u64::from(rankselect.pattern_10_word(word_index).count_ones());
+        }
+        superblocks.push(count);
+        Self { superblocks }
+    }
+
+    /// Returns the number of occurrences starting up to `index` (inclusive) or `None` if
+    /// `index` is out of bounds.
+    pub fn rank(&self, rankselect: &RankSelect, index: u64) -> Option<u64> {
+        if index >= rankselect.len {
+            return None;
+        }
+        let word = (index / 64) as usize;
+        let superblock = word / SUPERBLOCK_WORDS;
+        let mut rank = self.superblocks[superblock];
+        for w in superblock * SUPERBLOCK_WORDS..word {
+            rank += u64::from(rankselect.pattern_10_word(w).count_ones());
+        }
+        let offset = index % 64;
+        let mask = if offset == 63 {
+            !0
+        } else {
+            (1 << (offset + 1)) - 1
+        };
+        Some(rank + u64::from((rankselect.pattern_10_word(word) & mask).count_ones()))
+    }
+
+    /// Returns the position of the `rank`-th occurrence or `None` if there is no such
+    /// occurrence.
+    pub fn select(&self, rankselect: &RankSelect, rank: u64) -> Option<u64> {
+        select_with(
+            &|superblock| self.superblocks[superblock],
+            self.superblocks.len(),
+            &|word_index| rankselect.pattern_10_word(word_index),
+            rankselect.words.len(),
+            rank,
+        )
+    }
+}
+
+impl SpaceUsage for PatternRankSelect {
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            rank_directory: self.superblocks.len() * mem::size_of::<u64>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+/// Returns the position of the `rank`-th 1 in `word_count` words, given the number of 1s
+/// before each of the `superblock_count - 1` superblocks followed by the total number of 1s.
+fn select_with(
+    count: &dyn Fn(usize) -> u64,
+    superblock_count: usize,
+    word: &dyn Fn(usize) -> u64,
+    word_count: usize,
+    rank: u64,
+) -> Option<u64> {
+    if rank == 0 || rank > count(superblock_count - 1) {
+        return None;
+    }
+
+    // find the last superblock with less than `rank` matching bits before it
+    let (mut low, mut high) = (0, superblock_count - 1);
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        if count(middle) < rank {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    let mut remaining = rank - count(low);
+    for word_index in low * SUPERBLOCK_WORDS..word_count {
+        let word = word(word_index);
+        let ones = u64::from(word.count_ones());
+        if ones >= remaining {
+            return Some(word_index as u64 * 64 + select_in_word(word, remaining));
+        }
+        remaining -= ones;
+    }
+    None
+}
+
+/// Returns the position of the `rank`-th 1 in `word`, with `1 <= rank <= word.count_ones()`.
+fn select_in_word(mut word: u64, rank: u64) -> u64 {
+    for _ in 1..rank {
+        word &= word - 1;
+    }
+    u64::from(word.trailing_zeros())
+}
+
+impl SpaceUsage for RankSelect {
+    /// Select binary searches the rank directory, so there are no select samples.
+    fn space_usage(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            bits: self.words.len() * mem::size_of::<u64>(),
+            rank_directory: self.superblocks.len() * mem::size_of::<u64>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bv::{Bits, BitsMut};
+
+    fn example_bits() -> BitVec<u8> {
+        let mut bits = BitVec::new();
+        for i in 0..2000u64 {
+            bits.push(i % 3 == 0 || i % 7 == 0);
+        }
+        bits
+    }
+
+    #[test]
+    fn rank() {
+        let bits = example_bits();
+        let rankselect = RankSelect::new(&bits);
+        let mut ones = 0;
+        for i in 0..bits.len() {
+            if bits.get_bit(i) {
+                ones += 1;
+            }
+            assert_eq!(rankselect.rank_1(i), Some(ones));
+            assert_eq!(rankselect.rank_0(i), Some(i + 1 - ones));
+        }
+        assert_eq!(rankselect.rank_1(bits.len()), None);
+    }
+
+    #[test]
+    fn select() {
+        let bits = example_bits();
+        let rankselect = RankSelect::new(&bits);
+        let (mut ones, mut zeros) = (0, 0);
+        for i in 0..bits.len() {
+            if bits.get_bit(i) {
+                ones += 1;
+                assert_eq!(rankselect.select_1(ones), Some(i));
+            } else {
+                zeros += 1;
+                assert_eq!(rankselect.select_0(zeros), Some(i));
+            }
+        }
+        assert_eq!(rankselect.select_1(0), None);
+        assert_eq!(rankselect.select_0(0), None);
+        assert_eq!(rankselect.select_1(ones + 1), None);
+        assert_eq!(rankselect.select_0(zeros + 1), None);
+    }
+
+    #[test]
+    fn pattern_10() {
+        let mut bits = example_bits();
+        // the pattern reaches across a word boundary and the last bit is a 1
+        bits.set_bit(63, true);
+        bits.set_bit(64, false);
+        bits.push(true);
+        for len in [bits.len(), 1024, 64, 1] {
+            let mut bits = bits.clone();
+            bits.truncate(len);
+            let rankselect = RankSelect::new(&bits);
+            let pattern = PatternRankSelect::new(&rankselect);
+            let mut count = 0;
+            for i in 0..len {
+                if bits.get_bit(i) && i + 1 < len && !bits.get_bit(i + 1) {
+                    count += 1;
+                    assert_eq!(pattern.select(&rankselect, count), Some(i));
+                }
+                assert_eq!(pattern.rank(&rankselect, i), Some(count));
+            }
+            assert_eq!(pattern.rank(&rankselect, len), None);
+            assert_eq!(pattern.select(&rankselect, 0), None);
+            assert_eq!(pattern.select(&rankselect, count + 1), None);
+        }
+    }
+
+    #[test]
+    fn bits() {
+        let bits = bit_vec![true, true, false, true, false, false];
+        let rankselect = RankSelect::new(&bits);
+        assert_eq!(rankselect.len(), 6);
+        assert!(rankselect.get(3));
+        assert!(!rankselect.get(4));
+        assert_eq!(
+            format!("{:?}", rankselect.bits()),
+            "bit_vec![true, true, false, true, false, false]"
+        );
+        assert_eq!(rankselect.to_bitvec(), bits);
+    }
+
+    #[test]
+    fn from_parts() {
+        let rankselect = RankSelect::new(&example_bits());
+        let copy = RankSelect::from_parts(
+            rankselect.len(),
+            rankselect.words().clone(),
+            rankselect.superblocks().clone(),
+        )
+        .unwrap();
+        assert_eq!(copy.select_1(100), rankselect.select_1(100));
+        assert!(RankSelect::from_parts(
+            rankselect.len() + 64,
+            rankselect.words().clone(),
+            rankselect.superblocks().clone()
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Accounting of the memory used by the trees and their parts. Storage that is mapped from
+//! a file is counted like owned memory, bits shared by several parts are counted once.
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::bp_tree::BPTree;
+//! use fp_succinct_trees_1::common::space_usage::SpaceUsage;
+//!
+//! let bitvec = bit_vec![true, true, false, true, false, false];
+//! let tree: BPTree<i32> = BPTree::from_bitvec(bitvec).unwrap();
+//! let usage = tree.space_usage();
+//! assert_eq!(usage.bits, 8);
+//! assert_eq!(usage.labels, 0);
+//! println!("{} bits per node", usage.bits_per_node(3));
+//! # }
+//! ```
+
+use std::ops::Add;
+
+/// The number of bytes used by a structure, split by what they are used for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpaceBreakdown {
+    /// The bit vector itself
+    pub bits: usize,
+    /// Directories that answer rank queries, e.g. superblock counts
+    pub rank_directory: usize,
+    /// Sampled positions that answer select queries
+    pub select_samples: usize,
+    /// The nodes of the Range-Min-Max heap
+    pub minmax_heap: usize,
+    /// The labels of the nodes
+    pub labels: usize,
+}
+
+impl SpaceBreakdown {
+    /// Returns the sum of all parts in bytes.
+    pub fn total(&self) -> usize {
+        self.bits + self.rank_directory + self.select_samples + self.minmax_heap + self.labels
+    }
+
+    /// Returns the number of bits used per node of a tree with `nodes` nodes.
+    pub fn bits_per_node(&self, nodes: u64) -> f64 {
+        (self.total() * 8) as f64 / nodes as f64
+    }
+}
+
+impl Add for SpaceBreakdown {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            bits: self.bits + other.bits,
+            rank_directory: self.rank_directory + other.rank_directory,
+            select_samples: self.select_samples + other.select_samples,
+            minmax_heap: self.minmax_heap + other.minmax_heap,
+            labels: self.labels + other.labels,
+        }
+    }
+}
+
+/// Structures that can report the memory they use. The fixed size of the structure itself is
+/// not included, only the memory its fields refer to.
+pub trait SpaceUsage {
+    fn space_usage(&self) -> SpaceBreakdown;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bp_tree::BPTree;
+    use common::min_max::MinMax;
+    use common::rank_select::RankSelect;
+    use common::succinct_tree::SuccinctTree;
+    use common::test_util::arb_id_tree;
+    use dfuds_tree::DFUDSTree;
+    use louds_tree::LOUDSTree;
+    use proptest::prelude::*;
+    use std::mem;
+
+    #[test]
+    fn breakdown() {
+        let a = SpaceBreakdown {
+            bits: 1,
+            rank_directory: 2,
+            select_samples: 3,
+            minmax_heap: 4,
+            labels: 5,
+        };
+        assert_eq!(a.total(), 15);
+        assert_eq!((a + a).total(), 30);
+        assert_eq!((a + SpaceBreakdown::default()), a);
+        assert_eq!(a.bits_per_node(4), 30.0);
+    }
+
+    #[test]
+    fn parts() {
+        let bits = bit_vec![true, true, false, true, false, false];
+        let rankselect = RankSelect::new(&bits);
+        assert_eq!(
+            rankselect.space_usage(),
+            SpaceBreakdown {
+                bits: 8,
+                rank_directory: 16,
+                ..SpaceBreakdown::default()
+            }
+        );
+        let minmax = MinMax::new(bits, 4);
+        assert_eq!(minmax.space_usage().bits, 8);
+        assert_eq!(minmax.space_usage().minmax_heap, 3 * 40);
+    }
+
+    /// Checks the labels and that the structure of every tree stays below 5 bits per node
+    /// plus 16 words, which covers the padding of the heap to a power of two blocks.
+    fn check_tree<S: SuccinctTree<S, u32> + SpaceUsage>(tree: &S, nodes: u64) {
+        let usage = tree.space_usage();
+        assert_eq!(usage.labels, nodes as usize * mem::size_of::<u32>());
+        assert!(usage.bits * 8 >= 2 * nodes as usize);
+        let structure = usage.total() - usage.labels;
+        assert!(structure * 8 <= 5 * nodes as usize + 16 * 64);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn trees(id_tree in arb_id_tree(1200)) {
+            let root = id_tree.root_node_id().unwrap();
+            let nodes = id_tree.traverse_pre_order_ids(root).unwrap().count() as u64;
+            check_tree(&BPTree::<_>::from_id_tree(id_tree.clone()).unwrap(), nodes);
+            check_tree(&LOUDSTree::<_>::from_id_tree(id_tree.clone()).unwrap(), nodes);
+            check_tree(&DFUDSTree::from_id_tree(id_tree.clone()).unwrap(), nodes);
+        }
+    }
+}
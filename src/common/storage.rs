@@ -0,0 +1,254 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Read-only arrays that either own their memory or point into a memory-mapped file.
+//! Clones share the elements, so several structures can use the same bits without copying.
+//!
+//! Example
+//!
+//! ```
+//! use fp_succinct_trees_1::common::storage::Storage;
+//!
+//! let storage: Storage<u64> = Storage::from(vec![1, 2, 3]);
+//! assert_eq!(storage[1], 2);
+//! assert!(!storage.is_mapped());
+//! ```
+
+use bv::{BitVec, Bits};
+use memmap2::Mmap;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+use std::sync::Arc;
+
+/// Types that can be stored in a `Storage`.
+///
+/// # Safety
+///
+/// Implementors have to be `#[repr(C)]` (or primitive), must not contain padding, must have an
+/// alignment of at most 8 and every bit pattern has to be a valid value. Their little endian
+/// encoding has to be their in-memory layout on little endian machines.
+pub unsafe trait Plain: Copy {
+    /// Decodes a value from `mem::size_of::<Self>()` little endian bytes.
+    fn read_le(bytes: &[u8]) -> Self;
+    /// Appends the little endian encoding of the value.
+    fn write_le(&self, out: &mut Vec<u8>);
+}
+
+unsafe impl Plain for u64 {
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(&bytes[..8]);
+        u64::from_le_bytes(buffer)
+    }
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// A read-only array that is either owned or backed by a memory-mapped file. Cloning it is
+/// cheap, the clone refers to the same elements.
+pub struct Storage<T: Plain> {
+    repr: Repr<T>,
+}
+
+enum Repr<T> {
+    Owned(Arc<Vec<T>>),
+    Mapped {
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl<T: Plain> Storage<T> {
+    /// Creates a storage for `len` elements starting at byte `offset` of the mapped file.
+    /// Returns `None` if the elements are not aligned or do not lie completely inside of the
+    /// mapping, or if this is not a little endian machine.
+    pub(crate) fn mapped(map: &Arc<Mmap>, offset: usize, len: usize) -> Option<Self> {
+        let end = len
+            .checked_mul(mem::size_of::<T>())
+            .and_then(|size| size.checked_add(offset))?;
+        let aligned = (map.as_ptr() as usize + offset).is_multiple_of(mem::align_of::<T>());
+        if cfg!(target_endian = "little") && aligned && end <= map.len() {
+            Some(Self {
+                repr: Repr::Mapped {
+                    map: Arc::clone(map),
+                    offset,
+                    len,
+                },
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Decodes the elements from little endian bytes into an owned storage.
+    pub(crate) fn decode(bytes: &[u8]) -> Self {
+        let size = mem::size_of::<T>();
+        bytes
+            .chunks(size)
+            .filter(|chunk| chunk.len() == size)
+            .map(T::read_le)
+            .collect::<Vec<T>>()
+            .into()
+    }
+
+    /// Returns the little endian encoding of all elements.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len() * mem::size_of::<T>());
+        for element in self.iter() {
+            element.write_le(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Returns whether both storages refer to the same elements, e.g. because one is a clone
+    /// of the other.
+    pub fn shares_with(&self, other: &Self) -> bool {
+        self.as_ptr() == other.as_ptr() && self.len() == other.len()
+    }
+
+    /// Returns whether the elements are read from a memory-mapped file.
+    pub fn is_mapped(&self) -> bool {
+        match self.repr {
+            Repr::Owned(_) => false,
+            Repr::Mapped { .. } => true,
+        }
+    }
+}
+
+impl<T: Plain> Deref for Storage<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self.repr {
+            Repr::Owned(ref vec) => vec.as_slice(),
+            Repr::Mapped {
+                ref map,
+                offset,
+                len,
+            } => unsafe {
+                // `mapped()` checked bounds and alignment, `Plain` guarantees
+                // that every bit pattern is a valid `T`.
+                slice::from_raw_parts(map.as_ptr().add(offset) as *const T, len)
+            },
+        }
+    }
+}
+
+impl<T: Plain> From<Vec<T>> for Storage<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self {
+            repr: Repr::Owned(Arc::new(vec)),
+        }
+    }
+}
+
+impl<T: Plain> Clone for Storage<T> {
+    fn clone(&self) -> Self {
+        match self.repr {
+            Repr::Owned(ref vec) => Self {
+                repr: Repr::Owned(Arc::clone(vec)),
+            },
+            Repr::Mapped {
+                ref map,
+                offset,
+                len,
+            } => Self {
+                repr: Repr::Mapped {
+                    map: Arc::clone(map),
+                    offset,
+                    len,
+                },
+            },
+        }
+    }
+}
+
+impl<T: Plain + Debug> Debug for Storage<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Plain + Serialize> Serialize for Storage<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Plain + Deserialize<'de>> Deserialize<'de> for Storage<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<T>::deserialize(deserializer).map(Storage::from)
+    }
+}
+
+/// Packs a bit vector into 64 bit words, the first bit being the least significant bit of
+/// the first word. Unused bits of the last word are zero.
+pub fn words_from_bitvec<B: Bits<Block = u8> + ?Sized>(bits: &B) -> Vec<u64> {
+    let mut words = vec![0u64; bits.bit_len().div_ceil(64) as usize];
+    for block in 0..bits.block_len() {
+        words[block / 8] |= u64::from(bits.get_block(block)) << (8 * (block % 8));
+    }
+    if !bits.bit_len().is_multiple_of(64) {
+        let last = words.len() - 1;
+        words[last] &= (1 << (bits.bit_len() % 64)) - 1;
+    }
+    words
+}
+
+/// Unpacks the first `len` bits of 64 bit words into a bit vector.
+pub fn bitvec_from_words(words: &[u64], len: u64) -> BitVec<u8> {
+    let mut bits = BitVec::with_capacity(len);
+    for index in 0..len {
+        bits.push(words[(index / 64) as usize] >> (index % 64) & 1 == 1);
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned() {
+        let storage: Storage<u64> = Storage::from(vec![1, 2, 3]);
+        assert!(!storage.is_mapped());
+        assert_eq!(&*storage, &[1, 2, 3]);
+        assert_eq!(&*storage.clone(), &[1, 2, 3]);
+        assert!(storage.shares_with(&storage.clone()));
+        assert!(!storage.shares_with(&Storage::from(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn encode_decode() {
+        let storage: Storage<u64> = Storage::from(vec![1, 1 << 63, 42]);
+        let bytes = storage.encode();
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(bytes[8..16], [0, 0, 0, 0, 0, 0, 0, 0x80]);
+        assert_eq!(&*Storage::<u64>::decode(&bytes), &*storage);
+    }
+
+    #[test]
+    fn words() {
+        let bits = bit_vec![true, false, true, true, false, false, false, false, true];
+        let words = words_from_bitvec(&bits);
+        assert_eq!(words, vec![0b1_0000_1101]);
+        assert_eq!(bitvec_from_words(&words, bits.len()), bits);
+    }
+
+    #[test]
+    fn words_mask_unused_bits() {
+        let mut bits: BitVec<u8> = BitVec::new_fill(true, 8);
+        bits.truncate(3);
+        assert_eq!(words_from_bitvec(&bits), vec![0b111]);
+    }
+}
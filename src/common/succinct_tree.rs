@@ -1,10 +1,39 @@
-use bv::{BitVec, Bits};
+use bv::Bits;
 use common::errors::EmptyTreeError;
 use common::errors::NodeError;
-use id_tree::Tree;
+use common::traversal::{Ancestors, Children, Leaves, LevelOrder, PostOrder, PreOrder};
+use id_tree::InsertBehavior::{AsRoot, UnderNode};
+use id_tree::{Node, NodeId, Tree, TreeBuilder};
+use std::cmp::Ordering;
 use std::fmt::Debug;
 
+/// Identifies a node by its rank in the order of the representation: pre-order for BP and
+/// DFUDS, level order for LOUDS. The ranks of a tree are dense, so they can be used to index
+/// arrays of per-node data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeRank(pub u64);
+
+impl NodeRank {
+    /// Returns the rank as an index into a slice.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<u64> for NodeRank {
+    fn from(rank: u64) -> Self {
+        NodeRank(rank)
+    }
+}
+
+impl From<NodeRank> for u64 {
+    fn from(rank: NodeRank) -> Self {
+        rank.0
+    }
+}
+
 pub trait SuccinctTree<T, L>: Debug {
+    fn root(&self) -> u64;
     fn is_leaf(&self, index: u64) -> Result<bool, NodeError>;
     fn parent(&self, index: u64) -> Result<u64, NodeError>;
     fn first_child(&self, index: u64) -> Result<u64, NodeError>;
@@ -13,6 +42,134 @@ pub trait SuccinctTree<T, L>: Debug {
     fn child_label(&self, index: u64) -> Result<&L, NodeError>;
     fn labeled_child(&self, index: u64, label: L) -> Result<u64, NodeError>;
 
+    /// Returns the dense rank of the node, which does not depend on the bit positions of the
+    /// representation. The ranks of a tree with n nodes are 0 to n-1, the root has rank 0.
+    /// # Arguments
+    /// * `index` The index of the node
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn node_to_rank(&self, index: u64) -> Result<NodeRank, NodeError>;
+
+    /// Returns the index of the node with the given rank, the inverse of `node_to_rank`.
+    /// # Arguments
+    /// * `rank` The rank of the node
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no more than `rank` nodes.
+    fn rank_to_node(&self, rank: NodeRank) -> Result<u64, NodeError>;
+
+    /// Returns an iterator over the children of the node from left to right.
+    /// # Arguments
+    /// * `index` The index of the node
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn children(&self, index: u64) -> Result<Children<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        let first = if self.is_leaf(index)? {
+            None
+        } else {
+            Some(self.first_child(index)?)
+        };
+        Ok(Children::new(self, first))
+    }
+
+    /// Returns an iterator over the subtree of the node in pre-order, starting with the node.
+    /// # Arguments
+    /// * `index` The index of the subtree root
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn preorder(&self, index: u64) -> Result<PreOrder<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        self.is_leaf(index)?;
+        Ok(PreOrder::new(self, index))
+    }
+
+    /// Returns an iterator over the subtree of the node in post-order, ending with the node.
+    /// # Arguments
+    /// * `index` The index of the subtree root
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn postorder(&self, index: u64) -> Result<PostOrder<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        self.is_leaf(index)?;
+        Ok(PostOrder::new(self, index))
+    }
+
+    /// Returns an iterator over the subtree of the node in level order, starting with the node.
+    /// # Arguments
+    /// * `index` The index of the subtree root
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn level_order(&self, index: u64) -> Result<LevelOrder<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        self.is_leaf(index)?;
+        Ok(LevelOrder::new(self, index))
+    }
+
+    /// Returns an iterator over the ancestors of the node, from its parent up to the root.
+    /// # Arguments
+    /// * `index` The index of the node
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn ancestors(&self, index: u64) -> Result<Ancestors<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        self.is_leaf(index)?;
+        Ok(Ancestors::new(self, index))
+    }
+
+    /// Returns an iterator over the leaves in the subtree of the node from left to right.
+    /// # Arguments
+    /// * `index` The index of the subtree root
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn leaves(&self, index: u64) -> Result<Leaves<'_, Self, T, L>, NodeError>
+    where
+        Self: Sized,
+    {
+        self.is_leaf(index)?;
+        Ok(Leaves::new(self, index))
+    }
+
+    /// Expands the tree into an IDTree with the same shape and labels. This is the inverse of
+    /// `from_id_tree`.
+    /// # Errors
+    /// * `NoLabelError` If the tree has no labels, e.g. because it was created from a bit vector.
+    fn to_id_tree(&self) -> Result<Tree<L>, NodeError>
+    where
+        L: Clone,
+    {
+        let mut tree = TreeBuilder::new().build();
+        let root = self.root();
+        let root_id = tree
+            .insert(Node::new(self.child_label(root)?.clone()), AsRoot)
+            .unwrap();
+        let mut stack = vec![(root, root_id)];
+        while let Some((index, id)) = stack.pop() {
+            let mut child = if self.is_leaf(index)? {
+                None
+            } else {
+                Some(self.first_child(index)?)
+            };
+            while let Some(index) = child {
+                let child_id = tree
+                    .insert(Node::new(self.child_label(index)?.clone()), UnderNode(&id))
+                    .unwrap();
+                stack.push((index, child_id));
+                child = self.next_sibling(index).ok();
+            }
+        }
+        Ok(tree)
+    }
+
     /// Prüft ob ein Bitvector ein gültiger SuccinctTree ist, anhand des gültigen Exzesses und
     /// der Anzahl öffnender und schließender Klammern
     ///
@@ -20,26 +177,103 @@ pub trait SuccinctTree<T, L>: Debug {
     ///
     /// * `bitvec` - A bit vector.
     ///
-    fn is_valid(bitvec: &BitVec<u8>) -> bool {
+    fn is_valid<B: Bits + ?Sized>(bitvec: &B) -> bool {
+        Self::invalid_position(bitvec).is_none()
+    }
+
+    /// Returns the position of the first bit at which the bit vector stops being a valid
+    /// SuccinctTree, or the length of the bit vector if it ends too early. Returns `None` if
+    /// it is valid, see `is_valid`.
+    ///  # Arguments
+    ///
+    /// * `bitvec` - A bit vector.
+    ///
+    fn invalid_position<B: Bits + ?Sized>(bitvec: &B) -> Option<u64> {
         let mut excess = 0;
-        for i in 0..bitvec.len() {
+        for i in 0..bitvec.bit_len() {
             let x = bitvec.get_bit(i);
             if x {
                 excess += 1;
             } else {
                 excess -= 1;
             }
-            if excess == 0 && i < bitvec.len() - 1 {
-                return false;
+            // only the last bit may close the root
+            if excess < 0 || (excess == 0 && i < bitvec.bit_len() - 1) {
+                return Some(i);
             }
         }
         if excess != 0 {
-            return false;
+            return Some(bitvec.bit_len());
         }
-        true
+        None
     }
 
     fn calc_superblock_size(length: u64) -> f64 {
         ((length as f64).log2().powi(2) / 32.0).ceil()
     }
 }
+
+/// Compares two labels, used by trees whose children are sorted by their labels.
+pub type LabelOrder<L> = fn(&L, &L) -> Ordering;
+
+/// Sorts the children of every node of `tree` by their labels. The sort is stable, so
+/// children with equal labels keep their order.
+pub(crate) fn sort_children_by_label<L: Ord>(tree: &mut Tree<L>) {
+    let ids: Vec<NodeId> = match tree.root_node_id() {
+        Some(root) => tree.traverse_pre_order_ids(root).unwrap().collect(),
+        None => return,
+    };
+    for id in &ids {
+        tree.sort_children_by_data(id).unwrap();
+    }
+}
+
+/// Returns the rank of the first of `degree` sorted children with the given label, starting
+/// with 1, or `None` if there is none. `label_of` returns the label of the child with the
+/// given rank.
+pub(crate) fn search_sorted_children<'a, L: 'a, F>(
+    degree: u64,
+    order: LabelOrder<L>,
+    label: &L,
+    label_of: F,
+) -> Result<Option<u64>, NodeError>
+where
+    F: Fn(u64) -> Result<&'a L, NodeError>,
+{
+    // the first rank in [low, high) whose label is not less than `label`
+    let (mut low, mut high) = (1, degree + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if order(label_of(middle)?, label) == Ordering::Less {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    if low <= degree && order(label_of(low)?, label) == Ordering::Equal {
+        Ok(Some(low))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns whether the children of every node of `tree` are sorted by their labels.
+/// # Errors
+/// * `NoLabelError` If a node other than the root has no label.
+pub(crate) fn children_sorted_by_label<S, T, L>(tree: &S) -> Result<bool, NodeError>
+where
+    S: SuccinctTree<T, L>,
+    L: Ord,
+{
+    for index in tree.preorder(tree.root())? {
+        let mut previous: Option<&L> = None;
+        for child in tree.children(index)? {
+            let label = tree.child_label(child)?;
+            if previous.is_some_and(|previous| previous > label) {
+                return Ok(false);
+            }
+            previous = Some(label);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,95 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generators and helpers shared by the property tests of the trees.
+
+use common::errors::NodeError;
+use common::succinct_tree::SuccinctTree;
+use id_tree::InsertBehavior::{AsRoot, UnderNode};
+use id_tree::{Node, NodeId, Tree, TreeBuilder};
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+/// Generates ordered trees with up to `max_nodes` nodes, labeled with their insertion order.
+/// Each node is either appended below a random earlier node or below its predecessor, so both
+/// flat and deep trees are generated.
+pub fn arb_id_tree(max_nodes: usize) -> impl Strategy<Value = Tree<u32>> {
+    prop::collection::vec((any::<Index>(), any::<bool>()), 0..max_nodes).prop_map(|parents| {
+        let mut tree = TreeBuilder::new()
+            .with_node_capacity(parents.len() + 1)
+            .build();
+        let mut ids: Vec<NodeId> = vec![tree.insert(Node::new(0), AsRoot).unwrap()];
+        for (i, (parent, deep)) in parents.into_iter().enumerate() {
+            let parent = if deep { i } else { parent.index(i + 1) };
+            let id = tree
+                .insert(Node::new(i as u32 + 1), UnderNode(&ids[parent]))
+                .unwrap();
+            ids.push(id);
+        }
+        tree
+    })
+}
+
+/// Returns the labels and the number of children of all nodes in pre-order, which determines
+/// an ordered tree uniquely.
+pub fn pre_order_shape<L: Clone>(tree: &Tree<L>) -> Vec<(L, usize)> {
+    match tree.root_node_id() {
+        Some(root) => tree
+            .traverse_pre_order(root)
+            .unwrap()
+            .map(|node| (node.data().clone(), node.children().len()))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Replaces every label by its remainder modulo `alphabet_size`, so that siblings share labels.
+pub fn with_small_alphabet(mut tree: Tree<u32>, alphabet_size: u32) -> Tree<u32> {
+    let ids: Vec<NodeId> = match tree.root_node_id() {
+        Some(root) => tree.traverse_pre_order_ids(root).unwrap().collect(),
+        None => Vec::new(),
+    };
+    for id in ids {
+        let data = tree.get_mut(&id).unwrap().data_mut();
+        *data %= alphabet_size;
+    }
+    tree
+}
+
+/// Returns the first child of `index` with the given label by visiting all children.
+pub fn linear_labeled_child<S: SuccinctTree<T, u32>, T>(
+    tree: &S,
+    index: u64,
+    label: u32,
+) -> Option<u64> {
+    tree.children(index)
+        .unwrap()
+        .find(|child| *tree.child_label(*child).unwrap() == label)
+}
+
+/// A navigation method under test, reduced to whether it accepts an index.
+pub type IndexCheck<'a> = (&'static str, Box<dyn Fn(u64) -> Result<(), NodeError> + 'a>);
+
+/// Checks that every method rejects exactly the indices in `0..len` that are not in `nodes`
+/// with `NotANodeError`.
+pub fn check_node_indices(
+    len: u64,
+    nodes: &[u64],
+    checks: &[IndexCheck],
+) -> Result<(), TestCaseError> {
+    for index in 0..len {
+        let is_node = nodes.contains(&index);
+        for (name, check) in checks {
+            prop_assert_eq!(
+                check(index) == Err(NodeError::NotANodeError),
+                !is_node,
+                "{} at {}",
+                name,
+                index
+            );
+        }
+    }
+    Ok(())
+}
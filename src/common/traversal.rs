@@ -0,0 +1,337 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lazy traversals of the nodes of a `SuccinctTree`, created by its `children`, `preorder`,
+//! `postorder`, `level_order`, `ancestors` and `leaves` methods. All traversals except the
+//! level order only keep the current node and navigate with `first_child`, `next_sibling` and
+//! `parent`, so they need constant memory.
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::bp_tree::BPTree;
+//! use fp_succinct_trees_1::common::succinct_tree::SuccinctTree;
+//!
+//! let bitvec = bit_vec![true, true, false, true, true, false, false, false];
+//! let tree: BPTree<i32> = BPTree::from_bitvec(bitvec).unwrap();
+//! assert_eq!(tree.children(0).unwrap().collect::<Vec<_>>(), vec![1, 3]);
+//! assert_eq!(tree.preorder(0).unwrap().collect::<Vec<_>>(), vec![0, 1, 3, 4]);
+//! assert_eq!(tree.postorder(0).unwrap().collect::<Vec<_>>(), vec![1, 4, 3, 0]);
+//! assert_eq!(tree.ancestors(4).unwrap().collect::<Vec<_>>(), vec![3, 0]);
+//! # }
+//! ```
+
+use common::succinct_tree::SuccinctTree;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Iterator over the children of a node from left to right.
+pub struct Children<'a, S: 'a, T, L> {
+    tree: &'a S,
+    next: Option<u64>,
+    phantom: PhantomData<fn() -> (T, L)>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Children<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, first: Option<u64>) -> Self {
+        Self {
+            tree,
+            next: first,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for Children<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next?;
+        self.next = self.tree.next_sibling(current).ok();
+        Some(current)
+    }
+}
+
+/// Iterator over a subtree in pre-order.
+pub struct PreOrder<'a, S: 'a, T, L> {
+    tree: &'a S,
+    start: u64,
+    next: Option<u64>,
+    phantom: PhantomData<fn() -> (T, L)>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> PreOrder<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, start: u64) -> Self {
+        Self {
+            tree,
+            start,
+            next: Some(start),
+            phantom: PhantomData,
+        }
+    }
+
+    fn successor(&self, mut index: u64) -> Option<u64> {
+        if let Ok(child) = self.tree.first_child(index) {
+            return Some(child);
+        }
+        // go up until a node of the subtree has a next sibling
+        while index != self.start {
+            if let Ok(sibling) = self.tree.next_sibling(index) {
+                return Some(sibling);
+            }
+            index = self.tree.parent(index).ok()?;
+        }
+        None
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for PreOrder<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next?;
+        self.next = self.successor(current);
+        Some(current)
+    }
+}
+
+/// Iterator over a subtree in post-order.
+pub struct PostOrder<'a, S: 'a, T, L> {
+    tree: &'a S,
+    start: u64,
+    next: Option<u64>,
+    phantom: PhantomData<fn() -> (T, L)>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> PostOrder<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, start: u64) -> Self {
+        Self {
+            tree,
+            start,
+            next: Some(Self::leftmost_leaf(tree, start)),
+            phantom: PhantomData,
+        }
+    }
+
+    fn leftmost_leaf(tree: &S, mut index: u64) -> u64 {
+        while let Ok(child) = tree.first_child(index) {
+            index = child;
+        }
+        index
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for PostOrder<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next?;
+        self.next = if current == self.start {
+            None
+        } else if let Ok(sibling) = self.tree.next_sibling(current) {
+            Some(Self::leftmost_leaf(self.tree, sibling))
+        } else {
+            self.tree.parent(current).ok()
+        };
+        Some(current)
+    }
+}
+
+/// Iterator over a subtree in level order. It keeps the nodes of up to two levels whose
+/// children have not been visited yet.
+pub struct LevelOrder<'a, S: 'a, T, L> {
+    tree: &'a S,
+    queue: VecDeque<u64>,
+    phantom: PhantomData<fn() -> (T, L)>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> LevelOrder<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, start: u64) -> Self {
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        Self {
+            tree,
+            queue,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for LevelOrder<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.queue.pop_front()?;
+        let first = self.tree.first_child(current).ok();
+        self.queue
+            .extend(Children::<S, T, L>::new(self.tree, first));
+        Some(current)
+    }
+}
+
+/// Iterator over the proper ancestors of a node, starting with its parent and ending with
+/// the root.
+pub struct Ancestors<'a, S: 'a, T, L> {
+    tree: &'a S,
+    current: u64,
+    phantom: PhantomData<fn() -> (T, L)>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Ancestors<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, index: u64) -> Self {
+        Self {
+            tree,
+            current: index,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for Ancestors<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.current = self.tree.parent(self.current).ok()?;
+        Some(self.current)
+    }
+}
+
+/// Iterator over the leaves of a subtree from left to right.
+pub struct Leaves<'a, S: 'a, T, L> {
+    tree: &'a S,
+    preorder: PreOrder<'a, S, T, L>,
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Leaves<'a, S, T, L> {
+    pub(crate) fn new(tree: &'a S, start: u64) -> Self {
+        Self {
+            tree,
+            preorder: PreOrder::new(tree, start),
+        }
+    }
+}
+
+impl<'a, S: SuccinctTree<T, L>, T, L> Iterator for Leaves<'a, S, T, L> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let tree = self.tree;
+        self.preorder
+            .find(|index| tree.is_leaf(*index).unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bp_tree::BPTree;
+    use common::succinct_tree::SuccinctTree;
+    use common::test_util::arb_id_tree;
+    use dfuds_tree::DFUDSTree;
+    use id_tree::{NodeId, Tree};
+    use louds_tree::LOUDSTree;
+    use proptest::prelude::*;
+
+    fn labels<S: SuccinctTree<S, u32>, I: Iterator<Item = u64>>(tree: &S, nodes: I) -> Vec<u32> {
+        nodes
+            .map(|index| *tree.child_label(index).unwrap())
+            .collect()
+    }
+
+    fn id_labels<'a, I: Iterator<Item = &'a NodeId>>(tree: &Tree<u32>, ids: I) -> Vec<u32> {
+        ids.map(|id| *tree.get(id).unwrap().data()).collect()
+    }
+
+    /// Compares all traversals of every node of `tree` with the ones of `id_tree`.
+    fn check_traversals<S: SuccinctTree<S, u32>>(tree: &S, id_tree: &Tree<u32>) {
+        let root_id = id_tree.root_node_id().unwrap();
+        let nodes: Vec<u64> = tree.preorder(tree.root()).unwrap().collect();
+        let ids: Vec<NodeId> = id_tree.traverse_pre_order_ids(root_id).unwrap().collect();
+        assert_eq!(nodes.len(), ids.len());
+        for (index, id) in nodes.iter().zip(ids.iter()) {
+            let index = *index;
+            assert_eq!(
+                *tree.child_label(index).unwrap(),
+                *id_tree.get(id).unwrap().data()
+            );
+            assert_eq!(
+                labels(tree, tree.children(index).unwrap()),
+                id_labels(id_tree, id_tree.children_ids(id).unwrap())
+            );
+            assert_eq!(
+                labels(tree, tree.preorder(index).unwrap()),
+                id_labels(
+                    id_tree,
+                    id_tree
+                        .traverse_pre_order_ids(id)
+                        .unwrap()
+                        .collect::<Vec<_>>()
+                        .iter()
+                )
+            );
+            assert_eq!(
+                labels(tree, tree.postorder(index).unwrap()),
+                id_labels(
+                    id_tree,
+                    id_tree
+                        .traverse_post_order_ids(id)
+                        .unwrap()
+                        .collect::<Vec<_>>()
+                        .iter()
+                )
+            );
+            assert_eq!(
+                labels(tree, tree.level_order(index).unwrap()),
+                id_labels(
+                    id_tree,
+                    id_tree
+                        .traverse_level_order_ids(id)
+                        .unwrap()
+                        .collect::<Vec<_>>()
+                        .iter()
+                )
+            );
+            assert_eq!(
+                labels(tree, tree.ancestors(index).unwrap()),
+                id_labels(id_tree, id_tree.ancestor_ids(id).unwrap())
+            );
+            let leaves: Vec<u32> = id_tree
+                .traverse_pre_order(id)
+                .unwrap()
+                .filter(|node| node.children().is_empty())
+                .map(|node| *node.data())
+                .collect();
+            assert_eq!(labels(tree, tree.leaves(index).unwrap()), leaves);
+        }
+    }
+
+    #[test]
+    fn invalid_start() {
+        let tree: BPTree<u32> = BPTree::from_bitvec(bit_vec![true, false]).unwrap();
+        assert!(tree.children(2).is_err());
+        assert!(tree.preorder(2).is_err());
+        assert!(tree.postorder(2).is_err());
+        assert!(tree.level_order(2).is_err());
+        assert!(tree.ancestors(2).is_err());
+        assert!(tree.leaves(2).is_err());
+        assert_eq!(tree.ancestors(0).unwrap().count(), 0);
+        assert_eq!(tree.leaves(0).unwrap().collect::<Vec<_>>(), vec![0]);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn traversals(id_tree in arb_id_tree(60)) {
+            check_traversals(&BPTree::<_>::from_id_tree(id_tree.clone()).unwrap(), &id_tree);
+            check_traversals(&LOUDSTree::<_>::from_id_tree(id_tree.clone()).unwrap(), &id_tree);
+            check_traversals(&DFUDSTree::from_id_tree(id_tree.clone()).unwrap(), &id_tree);
+        }
+    }
+}
@@ -0,0 +1,779 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! DFUDS succinct tree implementation based on Benoit et al. (2005) and
+//! Jansson, Sadakane and Sung (2007).
+//!
+//! The nodes are written in pre-order, each node as one opening parenthesis per child
+//! followed by a closing one. With an additional opening parenthesis at the beginning the
+//! sequence is balanced, so the MinMax structure of BP can be used for navigation.
+//! A node is referenced by the position where its description starts, the root is at 1.
+//! `degree` and `child` find the end of the description by scanning its words, so they take
+//! one step per 64 children of the node instead of a rank and a select query.
+//!
+//! Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate bv;
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use bv::BitVec;
+//! use bv::Bits;
+//! use fp_succinct_trees_1::common::succinct_tree::SuccinctTree;
+//! use fp_succinct_trees_1::dfuds_tree::DFUDSTree;
+//!
+//! let bitvec = bit_vec!(true, true, true, false, false, false);
+//! let tree: DFUDSTree<i32> = DFUDSTree::from_bitvec(bitvec.clone()).unwrap();
+//! assert_eq!(tree.degree(1).unwrap(), 2);
+//! assert_eq!(tree.child(1, 2).unwrap(), 5);
+//! assert!(tree.is_leaf(4).unwrap());
+//! # }
+//! ```
+
+use bv::BitVec;
+use common::container::{Container, ContainerWriter, TreeKind};
+use common::errors::{EmptyTreeError, FileError, InvalidBitvecError, NodeError};
+use common::min_max::{MinMax, DEFAULT_BLOCK_SIZE};
+use common::parentheses::{read_bits, InputFormat};
+use common::rank_select::RankSelect;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::succinct_tree::{NodeRank, SuccinctTree};
+use id_tree::Tree;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::mem;
+
+pub struct DFUDSTree<L: PartialEq + Clone + Debug> {
+    labels: Vec<L>,
+    rankselect: RankSelect,
+    minmax: MinMax,
+}
+
+impl<L: PartialEq + Clone + Debug> PartialEq for DFUDSTree<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rankselect.bits() == other.rankselect.bits()
+    }
+}
+
+impl<L: PartialEq + Clone + Debug> SpaceUsage for DFUDSTree<L> {
+    /// The bits are shared by the rank/select and the MinMax structure and counted once.
+    fn space_usage(&self) -> SpaceBreakdown {
+        let mut usage = self.rankselect.space_usage();
+        usage.minmax_heap = self.minmax.space_usage().minmax_heap;
+        usage.labels = self.labels.len() * mem::size_of::<L>();
+        usage
+    }
+}
+
+impl<L: PartialEq + Clone + Debug> Debug for DFUDSTree<L> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "DFUDSTree\n  {{ bits: {:?} }}", self.rankselect.bits())
+    }
+}
+
+impl<L: PartialEq + Clone + Debug> SuccinctTree<DFUDSTree<L>, L> for DFUDSTree<L> {
+    /// Returns the index of the root node.
+    fn root(&self) -> u64 {
+        1
+    }
+
+    /// Checks if a node is a leaf.
+    /// # Arguments
+    /// * `index` The index of the node to check
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn is_leaf(&self, index: u64) -> Result<bool, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(!self.rankselect.get(index))
+    }
+
+    /// Returns the index of the parent of this node
+    /// # Arguments
+    /// * `index` The index of the node to get the parent of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `HasNoParentError` If `index` references the root node.
+    fn parent(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        if index == 1 {
+            return Err(NodeError::HasNoParentError);
+        }
+        // the parenthesis of the node in the description of its parent
+        let open = self.minmax.find_open(index - 1)?;
+        Ok(self
+            .pre_select(self.rankselect.rank_0(open).unwrap() + 1)
+            .unwrap())
+    }
+
+    /// Returns the index of the nodes first child.
+    /// # Arguments
+    /// * `index` The index of the node to get the first child of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NotAParentError` If `index` references a leaf.
+    fn first_child(&self, index: u64) -> Result<u64, NodeError> {
+        if self.is_leaf(index)? {
+            Err(NodeError::NotAParentError)
+        } else {
+            // the description of the first child follows right after the description of the node
+            Ok(self.next_0(index) + 1)
+        }
+    }
+
+    /// Returns the index of the next sibling
+    /// # Arguments
+    /// * `index` The index of the node to get the next sibling of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `HasNoParentError` If `index` references the root node.
+    /// * `NoSiblingError` If `index` has no further siblings.
+    fn next_sibling(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        if index == 1 {
+            return Err(NodeError::HasNoParentError);
+        }
+        // the parenthesis of the next sibling is left of the one of the node
+        let open = self.minmax.find_open(index - 1)?;
+        if open > 1 && self.rankselect.get(open - 1) {
+            Ok(self.minmax.find_close(open - 1)? + 1)
+        } else {
+            Err(NodeError::NoSiblingError)
+        }
+    }
+
+    /// Constructs a DFUDSTree from a IDTree
+    /// # Arguments
+    /// * `tree` The IDTree which should be converted
+    /// # Errors
+    /// * `EmptyTreeError` If `tree` does not contain any nodes.
+    fn from_id_tree(tree: Tree<L>) -> Result<Self, EmptyTreeError> {
+        let root = match tree.root_node_id() {
+            Some(id) => id,
+            None => return Err(EmptyTreeError),
+        };
+        let mut bitvec: BitVec<u8> = BitVec::new_fill(true, 1);
+        let mut labels = Vec::new();
+        for node in tree.traverse_pre_order(root).unwrap() {
+            for _ in 0..node.children().len() {
+                bitvec.push(true);
+            }
+            bitvec.push(false);
+            labels.push(node.data().clone());
+        }
+        Ok(Self::from_parts(bitvec, labels))
+    }
+
+    /// Returns the label for the edge between the parent and the node
+    /// # Arguments
+    /// * `index` The index of the node to get the label of
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoLabelError` If `index` does not reference a node with a label.
+    fn child_label(&self, index: u64) -> Result<&L, NodeError> {
+        let rank = self.pre_rank(index)?;
+        self.labels
+            .get((rank - 1) as usize)
+            .ok_or(NodeError::NoLabelError)
+    }
+
+    /// Returns the child from the specified node with that label
+    /// # Arguments
+    /// * `index` The index of the node to analyze
+    /// * `label` The label which a should have
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchChildError` If there is no child which has this label
+    fn labeled_child(&self, index: u64, label: L) -> Result<u64, NodeError> {
+        for n in 1..=self.degree(index)? {
+            let child = self.child(index, n)?;
+            if *self.child_label(child)? == label {
+                return Ok(child);
+            }
+        }
+        Err(NodeError::NoSuchChildError)
+    }
+
+    /// Returns the pre-order rank of the node, starting with 0 for the root.
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn node_to_rank(&self, index: u64) -> Result<NodeRank, NodeError> {
+        Ok(NodeRank(self.pre_rank(index)? - 1))
+    }
+
+    /// Returns the index of the node with the given pre-order rank.
+    /// # Arguments
+    /// * `rank` The rank of the node to get the index of.
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no more than `rank` nodes.
+    fn rank_to_node(&self, rank: NodeRank) -> Result<u64, NodeError> {
+        self.pre_select(rank.0 + 1)
+            .ok_or(NodeError::NoSuchNodeError)
+    }
+}
+
+impl<L: PartialEq + Clone + Debug> DFUDSTree<L> {
+    /// Returns whether the index is valid
+    /// # Arguments
+    /// * `index` The index which should be valid
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn is_valid_index(&self, index: u64) -> Result<bool, NodeError> {
+        // every node except the root starts right after the 0 that ends the previous node
+        if index >= self.rankselect.len()
+            || index == 0
+            || (index > 1 && self.rankselect.get(index - 1))
+        {
+            Err(NodeError::NotANodeError)
+        } else {
+            Ok(true)
+        }
+    }
+
+    /// Returns the position of the 0 that ends the description of the node at `index`. The
+    /// words are scanned from `index` on, which takes one step per 64 children.
+    fn next_0(&self, index: u64) -> u64 {
+        let words = self.rankselect.words();
+        let mut word = (index / 64) as usize;
+        let mut ones = u64::from((words[word] >> (index % 64)).trailing_ones());
+        let mut position = index + ones;
+        while position.is_multiple_of(64) && ones > 0 {
+            word += 1;
+            ones = u64::from(words[word].trailing_ones());
+            position += ones;
+        }
+        position
+    }
+
+    /// Returns the pre-order rank of the node, starting with 1 for the root.
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn pre_rank(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(self.rankselect.rank_0(index - 1).unwrap() + 1)
+    }
+
+    /// Returns the index of the node with this pre-order rank
+    /// # Arguments
+    /// * `rank` The rank of the node to get the index of.
+    ///
+    pub fn pre_select(&self, rank: u64) -> Option<u64> {
+        match rank {
+            0 => None,
+            // an empty tree has no root
+            1 => Some(1).filter(|index| *index < self.rankselect.len()),
+            _ => self
+                .rankselect
+                .select_0(rank - 1)
+                .map(|index| index + 1)
+                .filter(|index| *index < self.rankselect.len()),
+        }
+    }
+
+    /// Returns the number of children of the node
+    /// # Arguments
+    /// * `index` The index of the node to get the degree of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn degree(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(self.next_0(index) - index)
+    }
+
+    /// Returns the `n`-th child of the node, starting with 1 for the first child
+    /// # Arguments
+    /// * `index` The index of the node to get the child of.
+    /// * `n` The rank of the child
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchChildError` If the node has less than `n` children.
+    pub fn child(&self, index: u64, n: u64) -> Result<u64, NodeError> {
+        if n == 0 || n > self.degree(index)? {
+            return Err(NodeError::NoSuchChildError);
+        }
+        Ok(self.minmax.find_close(self.next_0(index) - n)? + 1)
+    }
+
+    /// Returns whether the node at `x` is an ancestor of the node `y`
+    /// # Arguments
+    /// * `x` The index of the node which should be ancestor
+    /// * `y` The index of the node which should be descendant
+    /// # Errors
+    /// * `NotANodeError` If `x` or `y` does not reference a node.
+    pub fn ancestor(&self, x: u64, y: u64) -> Result<bool, NodeError> {
+        self.is_valid_index(y)?;
+        Ok(x <= y && y <= self.subtree_end(x)?)
+    }
+
+    /// Returns the size of the subtree from this index
+    /// # Arguments
+    /// * `index` The index where the subtree size should be calculated.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn subtree_size(&self, index: u64) -> Result<u64, NodeError> {
+        Ok((self.subtree_end(index)? - index) / 2 + 1)
+    }
+
+    /// Returns the position of the 0 that ends the description of the last node in the
+    /// subtree of the node at `index`.
+    fn subtree_end(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        self.minmax
+            .fwd_search(index - 1, -1)
+            .ok_or(NodeError::NotANodeError)
+    }
+
+    /// Returns a DFUDSTree from a given BitVec
+    /// # Arguments
+    /// * `bitvec` The BitVec for the specified DFUDSTree, including the leading 1
+    /// # Errors
+    /// * `InvalidBitvecError` With the first invalid bit if `bitvec` does not describe a tree.
+    pub fn from_bitvec(bitvec: BitVec<u8>) -> Result<Self, InvalidBitvecError> {
+        if let Some(position) = Self::invalid_position(&bitvec as &BitVec<u8>) {
+            return Err(InvalidBitvecError { position });
+        }
+        Ok(Self::from_parts(bitvec, Vec::new()))
+    }
+
+    /// Reads a DFUDSTree from a parenthesis sequence, e.g. a file. The sequence is validated
+    /// while it is read.
+    /// # Arguments
+    /// * `reader` The input containing the sequence
+    /// * `format` Whether the input is text of `(` and `)` or packed bits
+    /// # Errors
+    /// * Every parse `FileError` with the byte offset of the first invalid input, see `read_bits`.
+    pub fn from_reader<R: Read>(reader: R, format: InputFormat) -> Result<Self, FileError> {
+        Ok(Self::from_parts(read_bits(reader, format)?, Vec::new()))
+    }
+
+    /// Creates a DFUDSTree from a valid bit vector and its labels in pre-order.
+    pub(crate) fn from_parts(bitvec: BitVec<u8>, labels: Vec<L>) -> Self {
+        let rankselect = RankSelect::new(&bitvec);
+        let minmax = MinMax::from_words(
+            rankselect.len(),
+            rankselect.words().clone(),
+            DEFAULT_BLOCK_SIZE,
+        );
+        Self {
+            rankselect,
+            labels,
+            minmax,
+        }
+    }
+}
+
+impl<L: PartialEq + Clone + Debug + Serialize + DeserializeOwned> DFUDSTree<L> {
+    /// Deserializes a DFUDSTree from a given file. Labels and, if the file contains it,
+    /// the precomputed MinMax heap are restored as well.
+    /// # Arguments
+    /// * `path` The path of the file to deserialize
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a DFUDSTree.
+    /// * `InvalidBitvecError` If the stored bits are not a valid DFUDSTree.
+    /// * `LabelCountError` If the number of labels does not match the number of nodes.
+    /// * Every other `FileError` if the file cannot be read or is corrupt.
+    pub fn from_file(path: String) -> Result<Self, FileError> {
+        let tree = Self::from_container(&Container::from_file(&path, TreeKind::DFUDS)?)?;
+        if let Some(position) = Self::invalid_position(&tree.rankselect.bits()) {
+            return Err(FileError::InvalidBitvecError { position });
+        }
+        Ok(tree)
+    }
+
+    /// Opens a DFUDSTree read-only from a memory-mapped file written by `save_to` or
+    /// `save_to_with_minmax`, see `BPTree::open_mapped`. Neither the checksum nor the
+    /// validity of the bits is checked.
+    /// # Arguments
+    /// * `path` The path of the file to map
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a DFUDSTree.
+    /// * Every other `FileError` if the file cannot be mapped or its header is corrupt.
+    /// # Safety
+    /// The file must not be modified or truncated while the tree is alive.
+    pub unsafe fn open_mapped(path: String) -> Result<Self, FileError> {
+        Self::from_container(&Container::open_mapped(&path, TreeKind::DFUDS)?)
+    }
+
+    fn from_container(container: &Container) -> Result<Self, FileError> {
+        let rankselect = container.rank_select()?;
+        let minmax = match container.minmax(&rankselect)? {
+            Some(minmax) => minmax,
+            None => MinMax::from_words(
+                rankselect.len(),
+                rankselect.words().clone(),
+                DEFAULT_BLOCK_SIZE,
+            ),
+        };
+        Ok(Self {
+            labels: container.labels()?,
+            rankselect,
+            minmax,
+        })
+    }
+
+    /// Serializes a DFUDSTree including its labels to a file. The MinMax heap is not saved
+    /// and will be rebuilt when loading the tree.
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    ///
+    pub fn save_to(&self, path: String) -> Result<(), FileError> {
+        self.write_to_file(&path, false)
+    }
+
+    /// Serializes a DFUDSTree including its labels and the precomputed MinMax heap to a file.
+    /// This results in a bigger file, but avoids rebuilding the heap in `from_file`.
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    ///
+    pub fn save_to_with_minmax(&self, path: String) -> Result<(), FileError> {
+        self.write_to_file(&path, true)
+    }
+
+    fn write_to_file(&self, path: &str, with_minmax: bool) -> Result<(), FileError> {
+        let len = self.rankselect.len();
+        let mut writer = ContainerWriter::new(TreeKind::DFUDS, len / 2, len);
+        writer.add_rank_select(&self.rankselect);
+        writer.add_labels(&self.labels)?;
+        if with_minmax {
+            writer.add_minmax(&self.minmax);
+        }
+        writer.write_to(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bp_tree::BPTree;
+    use common::test_util::{arb_id_tree, pre_order_shape};
+    use id_tree::InsertBehavior::{AsRoot, UnderNode};
+    use id_tree::{Node, NodeId, TreeBuilder};
+
+    /// root(a(c), b), i.e. `( (() () ) )` with the nodes at 1, 4, 6 and 7
+    fn example_tree() -> DFUDSTree<String> {
+        let mut id_tree: Tree<String> = TreeBuilder::new().with_node_capacity(4).build();
+        let root_id: NodeId = id_tree
+            .insert(Node::new(String::from("root")), AsRoot)
+            .unwrap();
+        let child_id = id_tree
+            .insert(Node::new(String::from("a")), UnderNode(&root_id))
+            .unwrap();
+        id_tree
+            .insert(Node::new(String::from("c")), UnderNode(&child_id))
+            .unwrap();
+        id_tree
+            .insert(Node::new(String::from("b")), UnderNode(&root_id))
+            .unwrap();
+        DFUDSTree::from_id_tree(id_tree).unwrap()
+    }
+
+    #[test]
+    fn new_from_bitvec() {
+        let bitvec = bit_vec!(true, true, false, false);
+        let tree: DFUDSTree<String> = DFUDSTree::from_bitvec(bitvec.clone()).unwrap();
+        assert_eq!(tree.rankselect.to_bitvec(), bitvec);
+    }
+
+    #[test]
+    fn new_from_bitvec_invalid() {
+        let tree: Result<DFUDSTree<String>, InvalidBitvecError> =
+            DFUDSTree::from_bitvec(bit_vec!(true, false, true, false));
+        assert_eq!(tree.unwrap_err(), InvalidBitvecError { position: 1 });
+    }
+
+    #[test]
+    fn from_id_tree() {
+        let tree = example_tree();
+        let expected: DFUDSTree<String> =
+            DFUDSTree::from_bitvec(bit_vec![true, true, true, false, true, false, false, false])
+                .unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn from_empty_id_tree() {
+        let id_tree: Tree<String> = TreeBuilder::new().with_node_capacity(5).build();
+        let tree: Result<DFUDSTree<String>, EmptyTreeError> = DFUDSTree::from_id_tree(id_tree);
+        assert_eq!(tree.unwrap_err(), EmptyTreeError);
+    }
+
+    #[test]
+    fn from_reader() {
+        let tree: DFUDSTree<String> =
+            DFUDSTree::from_reader("((()()))".as_bytes(), InputFormat::Parentheses).unwrap();
+        assert_eq!(tree, example_tree());
+        assert_eq!(tree.subtree_size(1).unwrap(), 4);
+    }
+
+    #[test]
+    fn is_leaf() {
+        let tree = example_tree();
+        assert!(!tree.is_leaf(1).unwrap());
+        assert!(!tree.is_leaf(4).unwrap());
+        assert!(tree.is_leaf(6).unwrap());
+        assert!(tree.is_leaf(7).unwrap());
+    }
+
+    #[test]
+    fn is_leaf_wrong_index() {
+        let tree = example_tree();
+        assert_eq!(tree.is_leaf(0).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(tree.is_leaf(2).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(tree.is_leaf(8).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn parent() {
+        let tree = example_tree();
+        assert_eq!(tree.parent(4).unwrap(), 1);
+        assert_eq!(tree.parent(6).unwrap(), 4);
+        assert_eq!(tree.parent(7).unwrap(), 1);
+        assert_eq!(tree.parent(1).unwrap_err(), NodeError::HasNoParentError);
+    }
+
+    #[test]
+    fn first_child() {
+        let tree = example_tree();
+        assert_eq!(tree.first_child(1).unwrap(), 4);
+        assert_eq!(tree.first_child(4).unwrap(), 6);
+        assert_eq!(tree.first_child(7).unwrap_err(), NodeError::NotAParentError);
+    }
+
+    #[test]
+    fn next_sibling() {
+        let tree = example_tree();
+        assert_eq!(tree.next_sibling(4).unwrap(), 7);
+        assert_eq!(tree.next_sibling(7).unwrap_err(), NodeError::NoSiblingError);
+        assert_eq!(tree.next_sibling(6).unwrap_err(), NodeError::NoSiblingError);
+        assert_eq!(
+            tree.next_sibling(1).unwrap_err(),
+            NodeError::HasNoParentError
+        );
+    }
+
+    #[test]
+    fn degree_and_child() {
+        let tree = example_tree();
+        assert_eq!(tree.degree(1).unwrap(), 2);
+        assert_eq!(tree.degree(4).unwrap(), 1);
+        assert_eq!(tree.degree(7).unwrap(), 0);
+        assert_eq!(tree.child(1, 1).unwrap(), 4);
+        assert_eq!(tree.child(1, 2).unwrap(), 7);
+        assert_eq!(tree.child(1, 3).unwrap_err(), NodeError::NoSuchChildError);
+        assert_eq!(tree.child(1, 0).unwrap_err(), NodeError::NoSuchChildError);
+    }
+
+    #[test]
+    fn degree_and_child_wide_nodes() {
+        // a node with `k` leaves at 1, below the root for `nested`, so that its description
+        // crosses word boundaries at different offsets
+        for k in [1, 62, 63, 64, 65, 130] {
+            for nested in [false, true] {
+                let mut bitvec: BitVec<u8> = bit_vec![true];
+                if nested {
+                    bitvec.push(true);
+                    bitvec.push(false);
+                }
+                let index = bitvec.len();
+                for _ in 0..k {
+                    bitvec.push(true);
+                }
+                for _ in 0..=k {
+                    bitvec.push(false);
+                }
+                let tree: DFUDSTree<String> = DFUDSTree::from_bitvec(bitvec).unwrap();
+                assert_eq!(tree.degree(index).unwrap(), k);
+                assert_eq!(tree.child(index, 1).unwrap(), index + k + 1);
+                assert_eq!(tree.child(index, k).unwrap(), index + 2 * k);
+                assert_eq!(tree.degree(index + 2 * k).unwrap(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn pre_rank_and_select() {
+        let tree = example_tree();
+        for (rank, index) in [1, 4, 6, 7].iter().enumerate() {
+            assert_eq!(tree.pre_rank(*index).unwrap(), rank as u64 + 1);
+            assert_eq!(tree.pre_select(rank as u64 + 1).unwrap(), *index);
+        }
+        assert_eq!(tree.pre_select(0), None);
+        assert_eq!(tree.pre_select(5), None);
+    }
+
+    #[test]
+    fn node_to_rank() {
+        let tree = example_tree();
+        for (rank, index) in [1, 4, 6, 7].iter().enumerate() {
+            assert_eq!(tree.node_to_rank(*index).unwrap(), NodeRank(rank as u64));
+            assert_eq!(tree.rank_to_node(NodeRank(rank as u64)).unwrap(), *index);
+        }
+        assert_eq!(tree.node_to_rank(2).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(
+            tree.rank_to_node(NodeRank(4)).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        let empty: DFUDSTree<String> = DFUDSTree::from_bitvec(BitVec::new()).unwrap();
+        assert_eq!(
+            empty.rank_to_node(NodeRank(0)).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        assert_eq!(empty.node_to_rank(1).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn ancestor() {
+        let tree = example_tree();
+        assert!(tree.ancestor(1, 7).unwrap());
+        assert!(tree.ancestor(4, 6).unwrap());
+        assert!(!tree.ancestor(4, 7).unwrap());
+        assert!(!tree.ancestor(6, 4).unwrap());
+    }
+
+    #[test]
+    fn subtree_size() {
+        let tree = example_tree();
+        assert_eq!(tree.subtree_size(1).unwrap(), 4);
+        assert_eq!(tree.subtree_size(4).unwrap(), 2);
+        assert_eq!(tree.subtree_size(6).unwrap(), 1);
+        assert_eq!(tree.subtree_size(7).unwrap(), 1);
+    }
+
+    #[test]
+    fn child_label() {
+        let tree = example_tree();
+        assert_eq!(*tree.child_label(1).unwrap(), "root");
+        assert_eq!(*tree.child_label(4).unwrap(), "a");
+        assert_eq!(*tree.child_label(6).unwrap(), "c");
+        assert_eq!(*tree.child_label(7).unwrap(), "b");
+    }
+
+    #[test]
+    fn labeled_child() {
+        let tree = example_tree();
+        assert_eq!(tree.labeled_child(1, String::from("b")).unwrap(), 7);
+        assert_eq!(tree.labeled_child(4, String::from("c")).unwrap(), 6);
+        assert_eq!(
+            tree.labeled_child(1, String::from("c")).unwrap_err(),
+            NodeError::NoSuchChildError
+        );
+    }
+
+    #[test]
+    fn print() {
+        let tree: DFUDSTree<String> =
+            DFUDSTree::from_bitvec(bit_vec![true, true, false, false]).unwrap();
+        assert_eq!(
+            format!("{:?}", tree),
+            "DFUDSTree\n  { bits: bit_vec![true, true, false, false] }"
+        );
+    }
+
+    #[test]
+    fn save_load() {
+        let tree = example_tree();
+        let path = "testdata/dfudstree.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: DFUDSTree<String> = DFUDSTree::from_file(path.to_string()).unwrap();
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        assert!(!result.minmax.heap().is_mapped());
+    }
+
+    #[test]
+    fn open_mapped() {
+        let tree = example_tree();
+        let path = "testdata/dfudstree_mapped.testdata";
+        tree.save_to_with_minmax(path.to_string()).unwrap();
+        let result: DFUDSTree<String> =
+            unsafe { DFUDSTree::open_mapped(path.to_string()).unwrap() };
+        assert!(result.rankselect.words().is_mapped());
+        assert!(result.minmax.heap().is_mapped());
+        assert_eq!(tree, result);
+        assert_eq!(result.parent(6).unwrap(), 4);
+        assert_eq!(result.next_sibling(4).unwrap(), 7);
+        assert_eq!(*result.child_label(7).unwrap(), "b");
+    }
+
+    #[test]
+    fn load_bp() {
+        let bp: BPTree<String> = BPTree::from_bitvec(bit_vec![true, false]).unwrap();
+        let path = "testdata/dfudstree_bp.testdata";
+        bp.save_to(path.to_string()).unwrap();
+        match DFUDSTree::<String>::from_file(path.to_string()) {
+            Err(FileError::WrongTreeKindError { expected, found }) => {
+                assert_eq!(expected, TreeKind::DFUDS);
+                assert_eq!(found, TreeKind::BP);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn to_id_tree_without_labels() {
+        let tree: DFUDSTree<String> = DFUDSTree::from_bitvec(bit_vec![true, false]).unwrap();
+        assert_eq!(tree.to_id_tree().unwrap_err(), NodeError::NoLabelError);
+    }
+
+    proptest! {
+        #[test]
+        fn node_ranks(id_tree in arb_id_tree(300)) {
+            let tree = DFUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let nodes: Vec<u64> = tree.preorder(tree.root()).unwrap().collect();
+            for (rank, index) in nodes.iter().enumerate() {
+                prop_assert_eq!(tree.node_to_rank(*index), Ok(NodeRank(rank as u64)));
+                prop_assert_eq!(tree.rank_to_node(NodeRank(rank as u64)), Ok(*index));
+            }
+            prop_assert_eq!(
+                tree.rank_to_node(NodeRank(nodes.len() as u64)),
+                Err(NodeError::NoSuchNodeError)
+            );
+            for index in 0..tree.rankselect.len() + 1 {
+                if !nodes.contains(&index) {
+                    prop_assert_eq!(tree.node_to_rank(index), Err(NodeError::NotANodeError));
+                }
+            }
+        }
+
+        #[test]
+        fn id_tree_round_trip(id_tree in arb_id_tree(300)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = DFUDSTree::from_id_tree(id_tree).unwrap();
+            prop_assert_eq!(pre_order_shape(&tree.to_id_tree().unwrap()), shape);
+        }
+
+        #[test]
+        fn navigation(id_tree in arb_id_tree(1500)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = DFUDSTree::from_id_tree(id_tree).unwrap();
+            for (rank, (label, degree)) in shape.iter().enumerate() {
+                let index = tree.pre_select(rank as u64 + 1).unwrap();
+                prop_assert_eq!(tree.child_label(index).unwrap(), label);
+                prop_assert_eq!(tree.degree(index).unwrap(), *degree as u64);
+                for n in 1..=*degree as u64 {
+                    let child = tree.child(index, n).unwrap();
+                    prop_assert_eq!(tree.parent(child).unwrap(), index);
+                    prop_assert!(tree.ancestor(index, child).unwrap());
+                    if n < *degree as u64 {
+                        prop_assert_eq!(
+                            tree.next_sibling(child).unwrap(),
+                            tree.child(index, n + 1).unwrap()
+                        );
+                    }
+                }
+                let end = index + 2 * tree.subtree_size(index).unwrap() - 1;
+                prop_assert!(end == tree.rankselect.len() || !tree.ancestor(index, end).unwrap());
+            }
+        }
+    }
+}
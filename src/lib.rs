@@ -3,22 +3,29 @@
 // This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! Succinct Tree library with implementations for the succinct trees LOUDS and BP
-//! and the Range-Min-Max data structure used by BP. LOUDS and BP also use the
-//! Rank/Select data structure from the Rust-Bio crate.
+//! Succinct Tree library with implementations for the succinct trees LOUDS, BP and DFUDS
+//! and the Range-Min-Max data structure used by BP and DFUDS. All trees also use a
+//! Rank/Select data structure, which like all other parts of the trees can be
+//! loaded from a memory-mapped file. `louds_trie` stores a static set of byte strings in a
+//! LOUDS tree, `succinct_map` maps such keys to values.
 //! Code examples can be found in the submodules.
 
-extern crate bio;
 #[macro_use]
 extern crate bv;
 extern crate id_tree;
 #[macro_use]
-extern crate failure;
-#[macro_use]
 extern crate serde_derive;
 extern crate bincode;
+extern crate memmap2;
 extern crate serde;
 
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+
 pub mod bp_tree;
 pub mod common;
+pub mod dfuds_tree;
 pub mod louds_tree;
+pub mod louds_trie;
+pub mod succinct_map;
@@ -24,52 +24,80 @@
 //! # }
 //! ```
 
-use bincode::{deserialize, serialize};
-use bio::data_structures::rank_select::RankSelect;
-use bv::{BitVec, Bits};
-use common::errors::{EmptyTreeError, InvalidBitvecError, NodeError};
-use common::succinct_tree::SuccinctTree;
-use failure::{Error, ResultExt};
+use bp_tree::BPTree;
+use bv::BitVec;
+use common::container::{Container, ContainerWriter, TreeKind};
+use common::errors::{EmptyTreeError, FileError, InvalidBitvecError, NodeError};
+use common::label_store::LabelStore;
+use common::parentheses::{read_bits, InputFormat};
+use common::rank_select::RankSelect;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::succinct_tree::{
+    children_sorted_by_label, search_sorted_children, sort_children_by_label, LabelOrder, NodeRank,
+    SuccinctTree,
+};
 use id_tree::Tree;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::io::Read;
+use std::mem;
+use std::sync::OnceLock;
 use std::vec::Vec;
 
-pub struct LOUDSTree<L> {
+/// A tree in level-order unary degree sequence. The labels of the nodes are kept in level
+/// order in a `LabelStore`, by default a plain `Vec<L>`.
+pub struct LOUDSTree<L, S: LabelStore<L> = Vec<L>> {
     rankselect: RankSelect,
-    labels: Vec<L>,
+    labels: S,
+    /// Set if the children of every node are sorted by their labels
+    label_order: Option<LabelOrder<L>>,
+    /// The level-order rank of the first node of every level, followed by the number of
+    /// nodes. Built on first use by `level_first` and `level_next`.
+    levels: OnceLock<Vec<u64>>,
 }
 
-impl<L: PartialEq + Clone + Debug> PartialEq for LOUDSTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> PartialEq for LOUDSTree<L, S> {
     fn eq(&self, other: &Self) -> bool {
         self.rankselect.bits() == other.rankselect.bits()
     }
 }
 
-impl<L: PartialEq + Clone + Debug> Debug for LOUDSTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> SpaceUsage for LOUDSTree<L, S> {
+    fn space_usage(&self) -> SpaceBreakdown {
+        let mut usage = self.rankselect.space_usage();
+        usage.rank_directory += self
+            .levels
+            .get()
+            .map_or(0, |levels| levels.len() * mem::size_of::<u64>());
+        usage.labels = self.labels.space_usage().total();
+        usage
+    }
+}
+
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> Debug for LOUDSTree<L, S> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "LOUDSTree\n  {{ bits: {:?} }}", self.rankselect.bits())
     }
 }
 
-impl<L: PartialEq + Clone + Debug> SuccinctTree<LOUDSTree<L>, L> for LOUDSTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> SuccinctTree<LOUDSTree<L, S>, L>
+    for LOUDSTree<L, S>
+{
+    /// Returns the index of the root node.
+    fn root(&self) -> u64 {
+        1
+    }
+
     /// Checks if a node is a leaf.
     /// # Arguments
     /// * `index` The index of the node to check
     /// # Errors
     /// * `NotANodeError` If `index` does not reference a node.
     fn is_leaf(&self, index: u64) -> Result<bool, NodeError> {
-        if index >= self.rankselect.bits().bit_len()
-            || index == 0
-            || (!self.rankselect.bits().get_bit(index) && self.rankselect.bits().get_bit(index - 1))
-        {
-            Err(NodeError::NotANodeError)
-        } else {
-            Ok(!self.rankselect.bits().get_bit(index))
-        }
+        self.is_valid_index(index)?;
+        Ok(!self.rankselect.get(index))
     }
 
     /// Returns the index of the parent of this node
@@ -79,18 +107,11 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<LOUDSTree<L>, L> for LOUDSTree<L
     /// * `NotANodeError` If `index` does not reference a node.
     /// * `HasNoParentError` If `index` references the root node.
     fn parent(&self, index: u64) -> Result<u64, NodeError> {
-        if index >= self.rankselect.bits().bit_len() || index == 0 {
-            Err(NodeError::NotANodeError)
-        } else if index == 1 {
-            Err(NodeError::RootNodeError)
+        self.is_valid_index(index)?;
+        if index == 1 {
+            Err(NodeError::HasNoParentError)
         } else {
-            Ok(self
-                .prev_0(
-                    self.rankselect
-                        .select_1(self.rankselect.rank_0(index).unwrap())
-                        .unwrap(),
-                )
-                .unwrap() + 1)
+            Ok(self.prev_0(self.node_bit(index).unwrap()).unwrap() + 1)
         }
     }
 
@@ -118,7 +139,11 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<LOUDSTree<L>, L> for LOUDSTree<L
         let sibling = self
             .rankselect
             .select_0(self.rankselect.rank_0(index - 1).unwrap() + 1)
-            .unwrap() + 1;
+            .unwrap()
+            + 1;
+        if sibling >= self.rankselect.len() {
+            return Err(NodeError::NoSiblingError);
+        }
         let parent_b = self.parent(sibling)?;
         if parent_a == parent_b {
             Ok(sibling)
@@ -146,11 +171,12 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<LOUDSTree<L>, L> for LOUDSTree<L
             bitvec.push(false);
         }
 
-        let mut l_tree = Self::from_bitvec(bitvec).unwrap();
-        for node in tree.traverse_level_order(root).unwrap() {
-            l_tree.labels.push((*node.data()).clone());
-        }
-        Ok(l_tree)
+        let labels = tree
+            .traverse_level_order(root)
+            .unwrap()
+            .map(|node| node.data().clone())
+            .collect();
+        Ok(Self::from_parts(bitvec, labels))
     }
 
     /// Returns the label for the edge between the parent and the node
@@ -159,57 +185,108 @@ impl<L: PartialEq + Clone + Debug> SuccinctTree<LOUDSTree<L>, L> for LOUDSTree<L
     /// # Errors
     /// * `NotANodeError` If `index` does not reference a node.
     fn child_label(&self, index: u64) -> Result<&L, NodeError> {
-        // child label(x) =
-        //L[rank ( (parent(x)) + child rank(x) − 1]
-        let parent =
-            if index != 1 { self.parent(index)? } else { 0 };
-        let child_rank = if index == 1 || self.degree(parent)? == 1 {
-            0
-        } else {
-            self.child_rank(index)
-                .ok_or(NodeError::NotANodeError)
-                .unwrap()
-        };
-        let parent_rank = self.rankselect.rank_1(parent).unwrap();
-        Ok(self
-            .labels
-            .get((parent_rank + child_rank - 1) as usize)
-            .ok_or(NodeError::NoLabelError)?)
+        self.is_leaf(index)?;
+        self.labels
+            .get(self.level_rank(index))
+            .ok_or(NodeError::NoLabelError)
     }
 
+    /// Returns the index of the first child with the given label. If the children are sorted
+    /// by their labels, they are binary searched. Otherwise the search is left to the label
+    /// store, as the labels of the children are consecutive in level order.
+    /// # Arguments
+    /// * `index` The index of the node to get the child of
+    /// * `label` The label of the child
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchChildError` If no child has the label.
     fn labeled_child(&self, index: u64, label: L) -> Result<u64, NodeError> {
         let child_count = self.degree(index)?;
-        for i in 1..=child_count {
-            let child_index = self.child(index, i).unwrap();
-            let mut label_index = self
-                .rankselect
-                .rank_0(child_index)
-                .ok_or(NodeError::NotANodeError)?;
-            if self.is_leaf(child_index).unwrap() {
-                label_index -= 1;
-            }
-            let my_label = &self.labels[label_index as usize];
-            if *my_label == label {
-                return Ok(child_index);
-            }
+        if child_count == 0 {
+            return Err(NodeError::NoSuchChildError);
+        }
+        let first = self.level_rank(self.child(index, 1).unwrap());
+        let rank = match self.label_order {
+            Some(order) => search_sorted_children(child_count, order, &label, |k| {
+                self.labels
+                    .get(first + k - 1)
+                    .ok_or(NodeError::NoLabelError)
+            })?
+            .map(|k| first + k - 1),
+            None => self.labels.find(first, first + child_count, &label),
+        };
+        match rank {
+            Some(rank) => Ok(self.child(index, rank - first + 1).unwrap()),
+            None => Err(NodeError::NoSuchChildError),
         }
-        Err(NodeError::NoSuchChildError)
+    }
+
+    /// Returns the rank of the node in level order, see `node_id`.
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    fn node_to_rank(&self, index: u64) -> Result<NodeRank, NodeError> {
+        self.node_id(index).map(NodeRank)
+    }
+
+    /// Returns the index of the node with the given rank in level order, see `node_select`.
+    /// # Arguments
+    /// * `rank` The rank of the node to get the index of.
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no more than `rank` nodes.
+    fn rank_to_node(&self, rank: NodeRank) -> Result<u64, NodeError> {
+        self.node_select(rank.0)
     }
 }
 
-impl<L: PartialEq + Clone + Debug> LOUDSTree<L> {
+impl<L: PartialEq + Clone + Debug, S: LabelStore<L>> LOUDSTree<L, S> {
+    /// Returns whether the index is valid
+    /// # Arguments
+    /// * `index` The index which should be valid
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn is_valid_index(&self, index: u64) -> Result<bool, NodeError> {
+        // every node except the root starts right after the 0 that ends the previous node
+        if index >= self.rankselect.len()
+            || index == 0
+            || (index > 1 && self.rankselect.get(index - 1))
+        {
+            Err(NodeError::NotANodeError)
+        } else {
+            Ok(true)
+        }
+    }
+
     fn prev_0(&self, index: u64) -> Option<u64> {
-        self.rankselect.select_0(self.rankselect.rank_0(index)?)
+        match self.rankselect.rank_0(index)? {
+            // the leading 1 takes the place of the 0 before the root
+            0 => Some(0),
+            rank => self.rankselect.select_0(rank),
+        }
+    }
+
+    /// Returns the position of the 1 that represents the node at `index` in the
+    /// description of its parent. The leading 1 represents the root.
+    fn node_bit(&self, index: u64) -> Option<u64> {
+        self.rankselect
+            .select_1(self.rankselect.rank_0(index - 1)? + 1)
     }
 
     fn next_0(&self, index: u64) -> Option<u64> {
         self.rankselect.select_0(self.rankselect.rank_0(index)? + 1)
     }
 
+    /// Returns the `n`-th child of the node, starting with 1 for the first child, or `None` if
+    /// `index` does not reference a node or the node has less than `n` children.
     pub fn child(&self, index: u64, n: u64) -> Option<u64> {
+        if n == 0 || n > self.degree(index).ok()? {
+            return None;
+        }
         Some(
             self.rankselect
-                .select_0(self.rankselect.rank_1(index)? + n - 2)? + 1,
+                .select_0(self.rankselect.rank_1(index)? + n - 2)?
+                + 1,
         )
     }
     pub fn degree(&self, index: u64) -> Result<u64, NodeError> {
@@ -220,57 +297,333 @@ impl<L: PartialEq + Clone + Debug> LOUDSTree<L> {
             Ok(self.next_0(index).ok_or(NodeError::NotANodeError)? - index)
         }
     }
+    /// Returns the rank of the node among its siblings, starting with 0 for the first child
+    /// and the root, or `None` if `index` does not reference a node. Like
+    /// `BPTree::child_rank`, the `n`-th child in `child` has the rank `n - 1`.
     pub fn child_rank(&self, index: u64) -> Option<u64> {
-        if index <= 1 {
+        self.is_valid_index(index).ok()?;
+        if index == 1 {
             return Some(0);
         }
-        let y = self
-            .rankselect
-            .select_1(self.rankselect.rank_0(index - 1)?)?;
-        Some(y - self.prev_0(y)?)
+        let y = self.node_bit(index)?;
+        Some(y - self.prev_0(y)? - 1)
     }
 
-    pub fn from_bitvec(bitvec: BitVec<u8>) -> Result<Self, InvalidBitvecError> {
-        if !Self::is_valid(&bitvec as &BitVec<u8>) {
-            return Err(InvalidBitvecError);
+    /// Returns the index of the previous sibling
+    /// # Arguments
+    /// * `index` The index of the node to get the previous sibling of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `HasNoParentError` If `index` references the root node.
+    /// * `NoSiblingError` If `index` is the first child of its parent.
+    pub fn prev_sibling(&self, index: u64) -> Result<u64, NodeError> {
+        self.parent(index)?;
+        // the 1s of the siblings are adjacent in the description of the parent, which starts
+        // right after a 0 or after the leading 1
+        let bit = self.node_bit(index).unwrap();
+        if bit > 1 && self.rankselect.get(bit - 1) {
+            Ok(self.level_select(self.level_rank(index) - 1).unwrap())
+        } else {
+            Err(NodeError::NoSiblingError)
         }
-        let superblock_size = Self::calc_superblock_size(bitvec.len());
+    }
 
-        Ok(Self {
-            labels: Vec::with_capacity(bitvec.len() as usize),
-            rankselect: RankSelect::new(bitvec, superblock_size as usize),
+    /// Returns the index of the nodes last child.
+    /// # Arguments
+    /// * `index` The index of the node to get the last child of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NotAParentError` If `index` references a leaf.
+    pub fn last_child(&self, index: u64) -> Result<u64, NodeError> {
+        match self.degree(index)? {
+            0 => Err(NodeError::NotAParentError),
+            degree => Ok(self.child(index, degree).unwrap()),
+        }
+    }
+
+    /// Returns the index of the first node with the given depth, the root having depth 0.
+    /// # Arguments
+    /// * `depth` The depth of the node
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no node with this depth.
+    pub fn level_first(&self, depth: u64) -> Result<u64, NodeError> {
+        let levels = self.levels();
+        if depth + 1 >= levels.len() as u64 {
+            return Err(NodeError::NoSuchNodeError);
+        }
+        self.node_select(levels[depth as usize])
+    }
+
+    /// Returns the next node to the right with the same depth, which is the next node in
+    /// level order unless `index` is the last node of its level.
+    /// # Arguments
+    /// * `index` The index of the node to get the neighbour of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    /// * `NoSuchNodeError` If there is no node to the right with the same depth.
+    pub fn level_next(&self, index: u64) -> Result<u64, NodeError> {
+        let rank = self.node_id(index)?;
+        let levels = self.levels();
+        // the start of the first level after the one of the node
+        let next_level = levels[levels.partition_point(|start| *start <= rank)];
+        if rank + 1 < next_level {
+            self.node_select(rank + 1)
+        } else {
+            Err(NodeError::NoSuchNodeError)
+        }
+    }
+
+    /// Returns the level-order ranks at which the levels start, followed by the number of
+    /// nodes. The children of the nodes before rank `r` are described by the 1s before the
+    /// `r`-th 0, so every level ends after as many nodes as there are 1s up to the 0 that
+    /// ends the previous level. An empty tree has no levels.
+    fn levels(&self) -> &[u64] {
+        self.levels.get_or_init(|| {
+            if self.rankselect.is_empty() {
+                return Vec::new();
+            }
+            let mut levels = vec![0];
+            let mut end = 1;
+            while end > levels[levels.len() - 1] {
+                levels.push(end);
+                end = self
+                    .rankselect
+                    .rank_1(self.rankselect.select_0(end).unwrap())
+                    .unwrap();
+            }
+            levels
         })
     }
 
-    pub fn from_file(path: String) -> Result<Self, Error> {
-        let file = fs::read(path).context("Could not read saved tree.")?;
-        let rankselect: RankSelect = deserialize(&file).context("Error while deserializing tree.")?;
+    /// Returns the rank of the node in level order, starting with 0 for the root.
+    /// # Arguments
+    /// * `index` The index of the node to get the rank of.
+    /// # Errors
+    /// * `NotANodeError` If `index` does not reference a node.
+    pub fn node_id(&self, index: u64) -> Result<u64, NodeError> {
+        self.is_valid_index(index)?;
+        Ok(self.level_rank(index))
+    }
+
+    /// Returns the index of the node with the given rank in level order, the inverse of
+    /// `node_id`.
+    /// # Arguments
+    /// * `rank` The rank of the node, starting with 0 for the root.
+    /// # Errors
+    /// * `NoSuchNodeError` If the tree has no more than `rank` nodes.
+    pub fn node_select(&self, rank: u64) -> Result<u64, NodeError> {
+        self.level_select(rank).ok_or(NodeError::NoSuchNodeError)
+    }
+
+    pub fn from_bitvec(bitvec: BitVec<u8>) -> Result<Self, InvalidBitvecError> {
+        if let Some(position) = Self::invalid_position(&bitvec as &BitVec<u8>) {
+            return Err(InvalidBitvecError { position });
+        }
+        Ok(Self::from_parts(bitvec, Vec::new()))
+    }
+
+    /// Reads a LOUDSTree from a parenthesis sequence, e.g. a file. The sequence is validated
+    /// while it is read.
+    /// # Arguments
+    /// * `reader` The input containing the sequence
+    /// * `format` Whether the input is text of `(` and `)` or packed bits
+    /// # Errors
+    /// * Every parse `FileError` with the byte offset of the first invalid input, see `read_bits`.
+    pub fn from_reader<R: Read>(reader: R, format: InputFormat) -> Result<Self, FileError> {
+        Ok(Self::from_parts(read_bits(reader, format)?, Vec::new()))
+    }
+
+    /// Creates a LOUDSTree from a valid bit vector and its labels in level order.
+    pub(crate) fn from_parts(bitvec: BitVec<u8>, labels: Vec<L>) -> Self {
+        Self {
+            labels: S::from_labels(labels),
+            rankselect: RankSelect::new(&bitvec),
+            label_order: None,
+            levels: OnceLock::new(),
+        }
+    }
+
+    /// Converts the tree into a BPTree with the same shape and labels. The tree is traversed
+    /// in pre-order by navigating the bit vector without a stack, so apart from the new tree
+    /// only a constant number of words is needed.
+    pub fn to_bp(&self) -> BPTree<L, S> {
+        if self.rankselect.is_empty() {
+            return BPTree::from_parts(BitVec::new(), Vec::new())
+                .with_label_order(self.label_order);
+        }
+        let root = self.root();
+        let mut bitvec = BitVec::with_capacity(self.rankselect.len());
+        let mut labels = Vec::with_capacity(self.labels.len() as usize);
+        let mut index = root;
+        loop {
+            bitvec.push(true);
+            if let Some(label) = self.labels.get(self.level_rank(index)) {
+                labels.push(label.clone());
+            }
+            if !self.is_leaf(index).unwrap() {
+                index = self.first_child(index).unwrap();
+                continue;
+            }
+            bitvec.push(false);
+            // close all nodes up to the first one with a next sibling
+            while index != root {
+                match self.next_sibling(index) {
+                    Ok(sibling) => {
+                        index = sibling;
+                        break;
+                    }
+                    Err(_) => {
+                        index = self.parent(index).unwrap();
+                        bitvec.push(false);
+                    }
+                }
+            }
+            if index == root {
+                return BPTree::from_parts(bitvec, labels).with_label_order(self.label_order);
+            }
+        }
+    }
+
+    /// Returns whether the children of every node are sorted by their labels, so that
+    /// `labeled_child` uses binary search.
+    pub fn has_sorted_children(&self) -> bool {
+        self.label_order.is_some()
+    }
+
+    pub(crate) fn with_label_order(mut self, label_order: Option<LabelOrder<L>>) -> Self {
+        self.label_order = label_order;
+        self
+    }
+
+    /// Returns the number of nodes before the node at `index` in level order.
+    pub(crate) fn level_rank(&self, index: u64) -> u64 {
+        self.rankselect.rank_0(index - 1).unwrap()
+    }
+
+    /// Returns the index of the node with `rank` nodes before it in level order. Every node
+    /// except the root starts right after the 0 that ends the previous node.
+    pub(crate) fn level_select(&self, rank: u64) -> Option<u64> {
+        let index = if rank == 0 {
+            self.root()
+        } else {
+            self.rankselect.select_0(rank)? + 1
+        };
+        // an empty tree has no root
+        Some(index).filter(|index| *index < self.rankselect.len())
+    }
+}
+
+impl<L: Ord + Clone + Debug, S: LabelStore<L>> LOUDSTree<L, S> {
+    /// Constructs a LOUDSTree from a IDTree with the children of every node sorted by their
+    /// labels, so `labeled_child` binary searches the children instead of scanning them.
+    /// Children with equal labels keep their order.
+    /// # Arguments
+    /// * `tree` The IDTree which should be converted
+    /// # Errors
+    /// * `EmptyTreeError` If `tree` does not contain any nodes.
+    pub fn from_id_tree_sorted(mut tree: Tree<L>) -> Result<Self, EmptyTreeError> {
+        sort_children_by_label(&mut tree);
+        Ok(Self::from_id_tree(tree)?.with_label_order(Some(L::cmp)))
+    }
+
+    /// Lets `labeled_child` binary search the children if the children of every node are
+    /// sorted by their labels, e.g. after loading a tree built by `from_id_tree_sorted`.
+    /// Returns whether they are sorted, which takes time linear in the number of nodes.
+    pub fn enable_sorted_children(&mut self) -> bool {
+        if children_sorted_by_label(self).unwrap_or(false) {
+            self.label_order = Some(L::cmp);
+        }
+        self.has_sorted_children()
+    }
+}
+
+impl<L, S> LOUDSTree<L, S>
+where
+    L: PartialEq + Clone + Debug + Serialize + DeserializeOwned,
+    S: LabelStore<L>,
+{
+    /// Deserializes a LOUDSTree including its labels from a given file
+    /// # Arguments
+    /// * `path` The path of the file to deserialize
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a LOUDSTree.
+    /// * `InvalidBitvecError` If the stored bits are not a valid LOUDSTree.
+    /// * `LabelCountError` If the number of labels does not match the number of nodes.
+    /// * Every other `FileError` if the file cannot be read or is corrupt.
+    pub fn from_file(path: String) -> Result<Self, FileError> {
+        Self::from_valid_container(&Container::from_file(&path, TreeKind::LOUDS)?)
+    }
+
+    /// Like `from_container`, but also checks that the stored bits are a valid LOUDSTree.
+    pub(crate) fn from_valid_container(container: &Container) -> Result<Self, FileError> {
+        let tree = Self::from_container(container)?;
+        if let Some(position) = Self::invalid_position(&tree.rankselect.bits()) {
+            return Err(FileError::InvalidBitvecError { position });
+        }
+        Ok(tree)
+    }
+
+    /// Opens a LOUDSTree read-only from a memory-mapped file written by `save_to`. The bits
+    /// and the rank/select directory are used directly from the file and loaded lazily on
+    /// first access, so opening takes constant time apart from decoding the labels. Neither
+    /// the checksum nor the validity of the bits is checked.
+    /// # Arguments
+    /// * `path` The path of the file to map
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a LOUDSTree.
+    /// * Every other `FileError` if the file cannot be mapped or its header is corrupt.
+    /// # Safety
+    /// The file must not be modified or truncated while the tree is alive.
+    pub unsafe fn open_mapped(path: String) -> Result<Self, FileError> {
+        Self::from_container(&Container::open_mapped(&path, TreeKind::LOUDS)?)
+    }
+
+    pub(crate) fn from_container(container: &Container) -> Result<Self, FileError> {
         Ok(Self {
-            labels: Vec::with_capacity(rankselect.bits().len() as usize),
-            rankselect,
+            rankselect: container.rank_select()?,
+            labels: S::from_labels(container.labels()?),
+            label_order: None,
+            levels: OnceLock::new(),
         })
     }
 
-    pub fn save_to(&self, path: String) -> Result<(), Error> {
-        let encoded = serialize(&self.rankselect).context("Error while serializing tree.")?;
-        let mut file = File::create(path).context("Could not save tree.")?;
-        file.write_all(&encoded)?;
-        Ok(())
+    /// Serializes a LOUDSTree including its labels to a file
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    ///
+    pub fn save_to(&self, path: String) -> Result<(), FileError> {
+        self.container_writer(TreeKind::LOUDS)?.write_to(&path)
+    }
+
+    /// Returns a writer with the bits and the labels of the tree, which structures built on
+    /// the tree extend by their own sections.
+    pub(crate) fn container_writer(&self, kind: TreeKind) -> Result<ContainerWriter, FileError> {
+        let len = self.rankselect.len();
+        let mut writer = ContainerWriter::new(kind, len / 2, len);
+        writer.add_rank_select(&self.rankselect);
+        writer.add_labels(&self.labels.to_vec())?;
+        Ok(writer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::label_store::{PackedLabels, WaveletLabels};
+    use common::test_util::{
+        arb_id_tree, check_node_indices, linear_labeled_child, pre_order_shape,
+        with_small_alphabet, IndexCheck,
+    };
     use id_tree::InsertBehavior::{AsRoot, UnderNode};
     use id_tree::{Node, NodeId, TreeBuilder};
+    use std::collections::HashMap;
 
     #[test]
     fn new_from_bitvec() {
         let bitvec = bit_vec![true, false];
         let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec.clone()).unwrap();
         assert_eq!(
-            *tree.rankselect.bits(),
+            tree.rankselect.to_bitvec(),
             bitvec,
             "BPTree seems to somehow change the bitvector it was created with."
         );
@@ -280,7 +633,27 @@ mod tests {
     fn new_from_bitvec_invalid() {
         let bitvec = bit_vec![true, true];
         let tree: Result<LOUDSTree<String>, InvalidBitvecError> = LOUDSTree::from_bitvec(bitvec);
-        assert_eq!(tree.unwrap_err(), InvalidBitvecError);
+        assert_eq!(tree.unwrap_err(), InvalidBitvecError { position: 2 });
+    }
+
+    #[test]
+    fn from_reader() {
+        let tree: LOUDSTree<String> =
+            LOUDSTree::from_reader("(((()()())))\n".as_bytes(), InputFormat::Parentheses).unwrap();
+        let expected = LOUDSTree::from_bitvec(bit_vec![
+            true, true, true, true, false, true, false, true, false, false, false, false
+        ])
+        .unwrap();
+        assert_eq!(tree, expected);
+        assert_eq!(tree.degree(1).unwrap(), 3);
+    }
+
+    #[test]
+    fn from_reader_invalid() {
+        match LOUDSTree::<String>::from_reader("((()".as_bytes(), InputFormat::Parentheses) {
+            Err(FileError::UnexpectedEndError(4)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
     }
 
     #[test]
@@ -299,10 +672,68 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error while deserializing tree.")]
+    fn save_load_labels() {
+        let mut id_tree: Tree<String> = TreeBuilder::new().with_node_capacity(5).build();
+        let root_id: NodeId = id_tree
+            .insert(Node::new(String::from("root")), AsRoot)
+            .unwrap();
+        let child_id = id_tree
+            .insert(
+                Node::new(String::from("first_root_child")),
+                UnderNode(&root_id),
+            )
+            .unwrap();
+        id_tree
+            .insert(Node::new(String::from("leaf")), UnderNode(&child_id))
+            .unwrap();
+        id_tree
+            .insert(
+                Node::new(String::from("second_root_child")),
+                UnderNode(&root_id),
+            )
+            .unwrap();
+        let tree = LOUDSTree::from_id_tree(id_tree).unwrap();
+        let path = "testdata/loudstree_labels.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: LOUDSTree<String> = LOUDSTree::from_file(path.to_string()).unwrap();
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        assert_eq!(*result.child_label(7).unwrap(), "leaf");
+        assert_eq!(
+            result
+                .labeled_child(1, String::from("second_root_child"))
+                .unwrap(),
+            6
+        );
+    }
+
+    #[test]
+    fn open_mapped() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let mut tree: LOUDSTree<i32> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        tree.labels = vec![1, 2, 3, 4, 5, 6];
+        let path = "testdata/loudstree_mapped.testdata";
+        tree.save_to(path.to_string()).unwrap();
+        let result: LOUDSTree<i32> = unsafe { LOUDSTree::open_mapped(path.to_string()).unwrap() };
+        assert!(result.rankselect.words().is_mapped());
+        assert!(result.rankselect.superblocks().is_mapped());
+        assert_eq!(tree, result);
+        assert_eq!(tree.labels, result.labels);
+        assert_eq!(result.first_child(1).unwrap(), 5);
+        assert_eq!(result.parent(5).unwrap(), 1);
+        assert_eq!(result.next_sibling(5).unwrap(), 7);
+        assert_eq!(result.degree(1).unwrap(), 3);
+    }
+
+    #[test]
     fn load_invalid() {
-        let _tree: LOUDSTree<String> =
-            LOUDSTree::from_file("testdata/loudstree_invalid.testdata".to_string()).unwrap();
+        let tree: Result<LOUDSTree<String>, FileError> =
+            LOUDSTree::from_file("testdata/loudstree_invalid.testdata".to_string());
+        match tree {
+            Err(FileError::NotATreeFileError) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
     }
 
     #[test]
@@ -358,7 +789,7 @@ mod tests {
     fn parent_root_node() {
         let bitvec = bit_vec![true, true, false, false];
         let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec.clone()).unwrap();
-        assert_eq!(tree.parent(1).unwrap_err(), NodeError::RootNodeError)
+        assert_eq!(tree.parent(1).unwrap_err(), NodeError::HasNoParentError)
     }
 
     #[test]
@@ -368,6 +799,24 @@ mod tests {
         assert_eq!(tree.parent(0).unwrap_err(), NodeError::NotANodeError)
     }
 
+    #[test]
+    fn not_a_node() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        for index in &[2, 3, 4, 6, 8] {
+            assert_eq!(tree.parent(*index).unwrap_err(), NodeError::NotANodeError);
+            assert_eq!(
+                tree.next_sibling(*index).unwrap_err(),
+                NodeError::NotANodeError
+            );
+            assert_eq!(tree.child(*index, 1), None);
+            assert_eq!(tree.child_rank(*index), None);
+        }
+        assert_eq!(tree.child(9, 1), None);
+        assert_eq!(tree.child(1, 4), None);
+    }
+
     #[test]
     fn next_sibling() {
         let bitvec =
@@ -398,6 +847,130 @@ mod tests {
         assert_eq!(tree.degree(9).unwrap(), 0);
     }
 
+    #[test]
+    fn prev_sibling() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        assert_eq!(tree.prev_sibling(9).unwrap(), 7);
+        assert_eq!(tree.prev_sibling(7).unwrap(), 5);
+        assert_eq!(tree.prev_sibling(5).unwrap_err(), NodeError::NoSiblingError);
+        assert_eq!(
+            tree.prev_sibling(11).unwrap_err(),
+            NodeError::NoSiblingError
+        );
+        assert_eq!(
+            tree.prev_sibling(1).unwrap_err(),
+            NodeError::HasNoParentError
+        );
+        assert_eq!(tree.prev_sibling(6).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn last_child() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        assert_eq!(tree.last_child(1).unwrap(), 9);
+        assert_eq!(tree.last_child(7).unwrap(), 11);
+        assert_eq!(tree.last_child(9).unwrap_err(), NodeError::NotAParentError);
+        assert_eq!(tree.last_child(12).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn levels() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        assert_eq!(tree.level_first(0).unwrap(), 1);
+        assert_eq!(tree.level_first(1).unwrap(), 5);
+        assert_eq!(tree.level_first(2).unwrap(), 10);
+        assert_eq!(tree.level_first(3).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_next(5).unwrap(), 7);
+        assert_eq!(tree.level_next(10).unwrap(), 11);
+        assert_eq!(tree.level_next(9).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_next(11).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_next(1).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_next(4).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn levels_empty_tree() {
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(BitVec::new()).unwrap();
+        assert_eq!(tree.level_first(0).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.level_next(1).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn levels_deep_tree() {
+        // a root with two chains of 20000 nodes each
+        let depth = 20_000;
+        let mut bitvec: BitVec<u8> = bit_vec![true, true, true, false];
+        for _ in 1..depth {
+            for _ in 0..2 {
+                bitvec.push(true);
+                bitvec.push(false);
+            }
+        }
+        bitvec.push(false);
+        bitvec.push(false);
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        assert_eq!(
+            tree.level_next(tree.root()).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        for level in 1..=depth {
+            let first = tree.level_first(level).unwrap();
+            let second = tree.level_next(first).unwrap();
+            assert_eq!(tree.node_id(second).unwrap(), 2 * level);
+            assert_eq!(tree.is_leaf(second).unwrap(), level == depth);
+            assert_eq!(
+                tree.level_next(second).unwrap_err(),
+                NodeError::NoSuchNodeError
+            );
+        }
+        assert_eq!(
+            tree.level_first(depth + 1).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+    }
+
+    #[test]
+    fn node_id() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        let nodes = [1, 5, 7, 9, 10, 11];
+        for (rank, index) in nodes.iter().enumerate() {
+            assert_eq!(tree.node_id(*index).unwrap(), rank as u64);
+            assert_eq!(tree.node_select(rank as u64).unwrap(), *index);
+        }
+        assert_eq!(tree.node_select(6).unwrap_err(), NodeError::NoSuchNodeError);
+        assert_eq!(tree.node_id(2).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(tree.node_id(0).unwrap_err(), NodeError::NotANodeError);
+    }
+
+    #[test]
+    fn node_to_rank() {
+        let bitvec =
+            bit_vec![true, true, true, true, false, true, false, true, false, false, false, false];
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bitvec).unwrap();
+        assert_eq!(tree.node_to_rank(1).unwrap(), NodeRank(0));
+        assert_eq!(tree.node_to_rank(9).unwrap(), NodeRank(3));
+        assert_eq!(tree.rank_to_node(NodeRank(4)).unwrap(), 10);
+        assert_eq!(tree.node_to_rank(3).unwrap_err(), NodeError::NotANodeError);
+        assert_eq!(
+            tree.rank_to_node(NodeRank(6)).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        let empty: LOUDSTree<String> = LOUDSTree::from_bitvec(BitVec::new()).unwrap();
+        assert_eq!(
+            empty.rank_to_node(NodeRank(0)).unwrap_err(),
+            NodeError::NoSuchNodeError
+        );
+        assert_eq!(empty.node_to_rank(1).unwrap_err(), NodeError::NotANodeError);
+    }
+
     #[test]
     fn child_rank() {
         let bitvec =
@@ -450,6 +1023,14 @@ mod tests {
         assert_eq!(tree.unwrap_err(), EmptyTreeError);
     }
 
+    #[test]
+    fn to_bp_empty_tree() {
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(BitVec::new()).unwrap();
+        let converted = tree.to_bp();
+        assert_eq!(converted, BPTree::from_bitvec(BitVec::new()).unwrap());
+        assert_eq!(converted.to_louds(), tree);
+    }
+
     #[test]
     fn child_label() {
         let mut id_tree: Tree<String> = TreeBuilder::new().with_node_capacity(5).build();
@@ -471,7 +1052,7 @@ mod tests {
                 UnderNode(&root_id),
             )
             .unwrap();
-        let bp_tree = LOUDSTree::from_id_tree(id_tree).unwrap();
+        let bp_tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
         assert_eq!(*bp_tree.child_label(1).unwrap(), "root");
         assert_eq!(*bp_tree.child_label(4).unwrap(), "first_root_child");
         assert_eq!(*bp_tree.child_label(6).unwrap(), "second_root_child");
@@ -499,7 +1080,7 @@ mod tests {
                 UnderNode(&root_id),
             )
             .unwrap();
-        let louds_tree = LOUDSTree::from_id_tree(id_tree).unwrap();
+        let louds_tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
         assert_eq!(
             louds_tree
                 .labeled_child(1, String::from("second_root_child"))
@@ -544,4 +1125,174 @@ mod tests {
         assert_eq!(tree3.child(1, 2).unwrap(), 6);
         assert_eq!(tree3.child(4, 1).unwrap(), 7);
     }
+
+    #[test]
+    fn to_id_tree_without_labels() {
+        let tree: LOUDSTree<String> = LOUDSTree::from_bitvec(bit_vec![true, false]).unwrap();
+        assert_eq!(tree.to_id_tree().unwrap_err(), NodeError::NoLabelError);
+    }
+
+    proptest! {
+        #[test]
+        fn strict_indices(id_tree in arb_id_tree(100)) {
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let nodes: Vec<u64> = tree.level_order(tree.root()).unwrap().collect();
+            let checks: Vec<IndexCheck> = vec![
+                ("is_leaf", Box::new(|i| tree.is_leaf(i).map(|_| ()))),
+                ("parent", Box::new(|i| tree.parent(i).map(|_| ()))),
+                ("first_child", Box::new(|i| tree.first_child(i).map(|_| ()))),
+                ("next_sibling", Box::new(|i| tree.next_sibling(i).map(|_| ()))),
+                ("child_label", Box::new(|i| tree.child_label(i).map(|_| ()))),
+                ("labeled_child", Box::new(|i| tree.labeled_child(i, 0).map(|_| ()))),
+                ("node_to_rank", Box::new(|i| tree.node_to_rank(i).map(|_| ()))),
+                ("degree", Box::new(|i| tree.degree(i).map(|_| ()))),
+                ("prev_sibling", Box::new(|i| tree.prev_sibling(i).map(|_| ()))),
+                ("last_child", Box::new(|i| tree.last_child(i).map(|_| ()))),
+                ("level_next", Box::new(|i| tree.level_next(i).map(|_| ()))),
+                ("node_id", Box::new(|i| tree.node_id(i).map(|_| ()))),
+                ("children", Box::new(|i| tree.children(i).map(|_| ()))),
+            ];
+            check_node_indices(tree.rankselect.len() + 2, &nodes, &checks)?;
+            for index in 0..tree.rankselect.len() + 2 {
+                let is_node = nodes.contains(&index);
+                prop_assert_eq!(tree.child_rank(index).is_some(), is_node);
+                if !is_node {
+                    prop_assert_eq!(tree.child(index, 1), None);
+                }
+            }
+        }
+
+        #[test]
+        fn node_ranks(id_tree in arb_id_tree(300)) {
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let nodes: Vec<u64> = tree.level_order(tree.root()).unwrap().collect();
+            for (rank, index) in nodes.iter().enumerate() {
+                prop_assert_eq!(tree.node_to_rank(*index), Ok(NodeRank(rank as u64)));
+                prop_assert_eq!(tree.rank_to_node(NodeRank(rank as u64)), Ok(*index));
+            }
+            prop_assert_eq!(
+                tree.rank_to_node(NodeRank(nodes.len() as u64)),
+                Err(NodeError::NoSuchNodeError)
+            );
+            for index in 0..tree.rankselect.len() + 1 {
+                if !nodes.contains(&index) {
+                    prop_assert_eq!(tree.node_to_rank(index), Err(NodeError::NotANodeError));
+                }
+            }
+        }
+
+        #[test]
+        fn to_bp(id_tree in arb_id_tree(1500)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let converted = tree.to_bp();
+            prop_assert_eq!(pre_order_shape(&converted.to_id_tree().unwrap()), shape);
+            prop_assert_eq!(converted.to_louds(), tree);
+        }
+
+        #[test]
+        fn id_tree_round_trip(id_tree in arb_id_tree(300)) {
+            let shape = pre_order_shape(&id_tree);
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            prop_assert_eq!(pre_order_shape(&tree.to_id_tree().unwrap()), shape);
+        }
+
+        #[test]
+        fn level_navigation(id_tree in arb_id_tree(300)) {
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree.clone()).unwrap();
+            let root = id_tree.root_node_id().unwrap();
+            let mut depths = HashMap::new();
+            let mut levels: Vec<Vec<u32>> = Vec::new();
+            for node in id_tree.traverse_level_order(root).unwrap() {
+                let depth = match node.parent() {
+                    Some(parent) => depths[id_tree.get(parent).unwrap().data()] + 1,
+                    None => 0,
+                };
+                depths.insert(*node.data(), depth);
+                if levels.len() <= depth {
+                    levels.push(Vec::new());
+                }
+                levels[depth].push(*node.data());
+            }
+            let label = |index: u64| *tree.child_label(index).unwrap();
+            for (depth, level) in levels.iter().enumerate() {
+                let mut index = tree.level_first(depth as u64).unwrap();
+                prop_assert_eq!(label(index), level[0]);
+                for expected in &level[1..] {
+                    index = tree.level_next(index).unwrap();
+                    prop_assert_eq!(label(index), *expected);
+                }
+                prop_assert_eq!(tree.level_next(index), Err(NodeError::NoSuchNodeError));
+            }
+            prop_assert!(tree.level_first(levels.len() as u64).is_err());
+            let order: Vec<u32> = levels.concat();
+            for (rank, expected) in order.iter().enumerate() {
+                let index = tree.node_select(rank as u64).unwrap();
+                prop_assert_eq!(label(index), *expected);
+                prop_assert_eq!(tree.node_id(index).unwrap(), rank as u64);
+                let children: Vec<u64> = tree.children(index).unwrap().collect();
+                prop_assert_eq!(tree.last_child(index).ok(), children.last().cloned());
+                for pair in children.windows(2) {
+                    prop_assert_eq!(tree.prev_sibling(pair[1]), Ok(pair[0]));
+                }
+                if let Some(first) = children.first() {
+                    prop_assert_eq!(tree.prev_sibling(*first), Err(NodeError::NoSiblingError));
+                }
+            }
+            prop_assert!(tree.node_select(order.len() as u64).is_err());
+        }
+
+        #[test]
+        fn label_stores(id_tree in arb_id_tree(300)) {
+            let tree = LOUDSTree::<_>::from_id_tree(id_tree).unwrap();
+            let bits = tree.rankselect.to_bitvec();
+            let labels: Vec<u32> = tree.labels.iter().map(|label| label % 3).collect();
+            let plain: LOUDSTree<u32> = LOUDSTree::from_parts(bits.clone(), labels.clone());
+            let packed: LOUDSTree<u32, PackedLabels<u32>> =
+                LOUDSTree::from_parts(bits.clone(), labels.clone());
+            let wavelet: LOUDSTree<u32, WaveletLabels<u32>> = LOUDSTree::from_parts(bits, labels);
+            for index in (1..plain.rankselect.len()).filter(|index| plain.is_leaf(*index).is_ok()) {
+                prop_assert_eq!(packed.child_label(index), plain.child_label(index));
+                prop_assert_eq!(wavelet.child_label(index), plain.child_label(index));
+                for label in 0..4 {
+                    prop_assert_eq!(
+                        wavelet.labeled_child(index, label),
+                        plain.labeled_child(index, label)
+                    );
+                    prop_assert_eq!(
+                        packed.labeled_child(index, label),
+                        plain.labeled_child(index, label)
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn sorted_children(id_tree in arb_id_tree(300)) {
+            let id_tree = with_small_alphabet(id_tree, 6);
+            let mut unsorted = LOUDSTree::<_>::from_id_tree(id_tree.clone()).unwrap();
+            let tree = LOUDSTree::<_>::from_id_tree_sorted(id_tree).unwrap();
+            prop_assert!(tree.has_sorted_children());
+            prop_assert!(!unsorted.has_sorted_children());
+            let nodes: Vec<u64> = tree.preorder(tree.root()).unwrap().collect();
+            for index in nodes {
+                let labels: Vec<u32> = tree
+                    .children(index)
+                    .unwrap()
+                    .map(|child| *tree.child_label(child).unwrap())
+                    .collect();
+                prop_assert!(labels.windows(2).all(|pair| pair[0] <= pair[1]));
+                for label in 0..7 {
+                    prop_assert_eq!(
+                        tree.labeled_child(index, label).ok(),
+                        linear_labeled_child(&tree, index, label)
+                    );
+                }
+            }
+            let sorted = unsorted.enable_sorted_children();
+            prop_assert_eq!(sorted, unsorted.has_sorted_children());
+            prop_assert_eq!(sorted, children_sorted_by_label(&unsorted).unwrap());
+            prop_assert!(tree.to_bp().has_sorted_children());
+        }
+    }
 }
@@ -0,0 +1,449 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A static set of byte strings stored as a trie in a `LOUDSTree<u8>`. The edge labels are
+//! the bytes of the keys, the nodes that end a key are marked in a separate bit vector.
+//!
+//! Every key has an id from `0..len()`, which is the number of keys whose nodes come before
+//! its node in level order. The ids of shorter keys are smaller than those of longer keys,
+//! so they are dense but not sorted lexicographically. The children of every node are sorted
+//! by their labels, so `labeled_child` binary searches them.
+//!
+//! Example
+//!
+//! ```
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::louds_trie::LOUDSTrie;
+//!
+//! let trie: LOUDSTrie = LOUDSTrie::from_keys(vec!["an", "and", "ant", "bee"]);
+//! let id = trie.lookup(b"and").unwrap();
+//! assert_eq!(trie.key(id).unwrap(), b"and");
+//! assert!(!trie.contains(b"a"));
+//!
+//! let predicted: Vec<Vec<u8>> = trie
+//!     .predictive_search(b"an")
+//!     .map(|id| trie.key(id).unwrap())
+//!     .collect();
+//! assert_eq!(predicted, vec![b"an".to_vec(), b"and".to_vec(), b"ant".to_vec()]);
+//! assert_eq!(trie.common_prefix_search(b"ants").count(), 2);
+//! # }
+//! ```
+
+use bv::BitVec;
+use common::container::{Container, ContainerWriter, TreeKind};
+use common::errors::FileError;
+use common::label_store::LabelStore;
+use common::rank_select::RankSelect;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use common::succinct_tree::SuccinctTree;
+use louds_tree::LOUDSTree;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+
+/// A static set of byte strings stored as a trie. The labels of the trie are kept in a
+/// `LabelStore`, by default a plain `Vec<u8>`.
+pub struct LOUDSTrie<S: LabelStore<u8> = Vec<u8>> {
+    tree: LOUDSTree<u8, S>,
+    /// One bit per node in level order, set if the node ends a key
+    terminals: RankSelect,
+}
+
+impl<S: LabelStore<u8>> Debug for LOUDSTrie<S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LOUDSTrie\n  {{ tree: {:?}, terminals: {:?} }}",
+            self.tree,
+            self.terminals.bits()
+        )
+    }
+}
+
+impl<S: LabelStore<u8>> SpaceUsage for LOUDSTrie<S> {
+    /// The terminal bit vector is counted like the bits of the tree.
+    fn space_usage(&self) -> SpaceBreakdown {
+        self.tree.space_usage() + self.terminals.space_usage()
+    }
+}
+
+impl<S: LabelStore<u8>> LOUDSTrie<S> {
+    /// Builds the trie of the given keys. The keys are sorted and duplicates are removed, so
+    /// they may be given in any order.
+    /// # Arguments
+    /// * `keys` The byte strings to store, e.g. `&str`, `String` or `Vec<u8>`
+    pub fn from_keys<I, K>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let mut keys: Vec<K> = keys.into_iter().collect();
+        keys.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        keys.dedup_by(|a, b| a.as_ref() == b.as_ref());
+        let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+
+        // every node is the range of keys that start with its path, the root has no label
+        let mut bitvec: BitVec<u8> = BitVec::new_fill(true, 1);
+        let mut labels = vec![0];
+        let mut terminals = BitVec::new();
+        let mut level = vec![(0, keys.len())];
+        let mut depth = 0;
+        while !level.is_empty() {
+            let mut next_level = Vec::new();
+            for (mut start, end) in level {
+                // the key that ends at this node is the first one of the range
+                let terminal = start < end && keys[start].len() == depth;
+                terminals.push(terminal);
+                if terminal {
+                    start += 1;
+                }
+                while start < end {
+                    let label = keys[start][depth];
+                    let mut child_end = start + 1;
+                    while child_end < end && keys[child_end][depth] == label {
+                        child_end += 1;
+                    }
+                    bitvec.push(true);
+                    labels.push(label);
+                    next_level.push((start, child_end));
+                    start = child_end;
+                }
+                bitvec.push(false);
+            }
+            level = next_level;
+            depth += 1;
+        }
+        Self {
+            tree: LOUDSTree::from_parts(bitvec, labels).with_label_order(Some(u8::cmp)),
+            terminals: RankSelect::new(&terminals),
+        }
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> u64 {
+        self.terminals.rank_1(self.terminals.len() - 1).unwrap()
+    }
+
+    /// Returns whether the trie contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the underlying tree.
+    pub fn tree(&self) -> &LOUDSTree<u8, S> {
+        &self.tree
+    }
+
+    /// Returns whether `key` is one of the keys.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    /// Returns the id of `key`, or `None` if it is not one of the keys.
+    pub fn lookup(&self, key: &[u8]) -> Option<u64> {
+        self.key_id(self.find_node(key)?)
+    }
+
+    /// Returns the key with the given id, or `None` if there is no such key.
+    pub fn key(&self, id: u64) -> Option<Vec<u8>> {
+        let mut index = self.tree.level_select(self.terminals.select_1(id + 1)?)?;
+        let mut key = Vec::new();
+        while index != self.tree.root() {
+            key.push(*self.tree.child_label(index).ok()?);
+            index = self.tree.parent(index).ok()?;
+        }
+        key.reverse();
+        Some(key)
+    }
+
+    /// Returns the ids of all keys that start with `prefix`, in lexicographic order of the
+    /// keys.
+    pub fn predictive_search(&self, prefix: &[u8]) -> PredictiveSearch<'_, S> {
+        let start = self.find_node(prefix);
+        PredictiveSearch {
+            trie: self,
+            start: start.unwrap_or(0),
+            next: start,
+        }
+    }
+
+    /// Returns the ids of all keys that are a prefix of `text`, from the shortest to the
+    /// longest key.
+    pub fn common_prefix_search<'a>(&'a self, text: &'a [u8]) -> CommonPrefixSearch<'a, S> {
+        CommonPrefixSearch {
+            trie: self,
+            text,
+            depth: 0,
+            node: Some(self.tree.root()),
+        }
+    }
+
+    /// Returns the node that ends the path of `key`, or `None` if there is no such path.
+    fn find_node(&self, key: &[u8]) -> Option<u64> {
+        let mut index = self.tree.root();
+        for label in key {
+            index = self.tree.labeled_child(index, *label).ok()?;
+        }
+        Some(index)
+    }
+
+    /// Returns the id of the key ending at the node at `index`, or `None` if no key ends there.
+    fn key_id(&self, index: u64) -> Option<u64> {
+        let rank = self.tree.level_rank(index);
+        if self.terminals.get(rank) {
+            Some(self.terminals.rank_1(rank).unwrap() - 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<S: LabelStore<u8>> LOUDSTrie<S> {
+    /// Deserializes a LOUDSTrie from a given file
+    /// # Arguments
+    /// * `path` The path of the file to deserialize
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a LOUDSTrie.
+    /// * `InvalidBitvecError` If the stored bits are not a valid LOUDSTree.
+    /// * `InvalidTreeError` If the children of the stored tree are not sorted by their labels.
+    /// * Every other `FileError` if the file cannot be read or is corrupt.
+    pub fn from_file(path: String) -> Result<Self, FileError> {
+        Self::from_valid_container(&Container::from_file(&path, TreeKind::Trie)?)
+    }
+
+    /// Opens a LOUDSTrie read-only from a memory-mapped file written by `save_to`, like
+    /// `LOUDSTree::open_mapped`. Neither the checksum nor the validity of the trie is checked.
+    /// # Arguments
+    /// * `path` The path of the file to map
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a LOUDSTrie.
+    /// * Every other `FileError` if the file cannot be mapped or its header is corrupt.
+    /// # Safety
+    /// The file must not be modified or truncated while the trie is alive.
+    pub unsafe fn open_mapped(path: String) -> Result<Self, FileError> {
+        let container = Container::open_mapped(&path, TreeKind::Trie)?;
+        Ok(Self {
+            tree: LOUDSTree::from_container(&container)?.with_label_order(Some(u8::cmp)),
+            terminals: container.terminals()?,
+        })
+    }
+
+    /// Serializes a LOUDSTrie to a file
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    pub fn save_to(&self, path: String) -> Result<(), FileError> {
+        self.container_writer(TreeKind::Trie)?.write_to(&path)
+    }
+
+    /// Returns a writer with the tree and the terminal flags of the trie.
+    pub(crate) fn container_writer(&self, kind: TreeKind) -> Result<ContainerWriter, FileError> {
+        let mut writer = self.tree.container_writer(kind)?;
+        writer.add_terminals(&self.terminals);
+        Ok(writer)
+    }
+
+    /// Reads the trie from a container and checks that its bits are a valid LOUDSTree whose
+    /// children are sorted by their labels.
+    pub(crate) fn from_valid_container(container: &Container) -> Result<Self, FileError> {
+        let mut tree = LOUDSTree::from_valid_container(container)?;
+        if !tree.enable_sorted_children() {
+            return Err(FileError::InvalidTreeError);
+        }
+        Ok(Self {
+            tree,
+            terminals: container.terminals()?,
+        })
+    }
+}
+
+/// Iterator over the ids of the keys below a node in pre-order, see
+/// `LOUDSTrie::predictive_search`. The nodes are visited by navigating the tree, so no stack is
+/// needed.
+pub struct PredictiveSearch<'a, S: LabelStore<u8> + 'a> {
+    trie: &'a LOUDSTrie<S>,
+    start: u64,
+    next: Option<u64>,
+}
+
+impl<'a, S: LabelStore<u8>> Iterator for PredictiveSearch<'a, S> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let tree = &self.trie.tree;
+        while let Some(index) = self.next {
+            self.next = if !tree.is_leaf(index).unwrap() {
+                tree.first_child(index).ok()
+            } else {
+                // go up to the first node with a next sibling
+                let mut node = index;
+                loop {
+                    if node == self.start {
+                        break None;
+                    }
+                    if let Ok(sibling) = tree.next_sibling(node) {
+                        break Some(sibling);
+                    }
+                    node = tree.parent(node).unwrap();
+                }
+            };
+            if let Some(id) = self.trie.key_id(index) {
+                return Some(id);
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the ids of the keys that are a prefix of a text, see
+/// `LOUDSTrie::common_prefix_search`.
+pub struct CommonPrefixSearch<'a, S: LabelStore<u8> + 'a> {
+    trie: &'a LOUDSTrie<S>,
+    text: &'a [u8],
+    depth: usize,
+    node: Option<u64>,
+}
+
+impl<'a, S: LabelStore<u8>> Iterator for CommonPrefixSearch<'a, S> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let tree = &self.trie.tree;
+        while let Some(index) = self.node {
+            self.node = self
+                .text
+                .get(self.depth)
+                .and_then(|label| tree.labeled_child(index, *label).ok());
+            self.depth += 1;
+            if let Some(id) = self.trie.key_id(index) {
+                return Some(id);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::label_store::PackedLabels;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    fn example() -> LOUDSTrie {
+        LOUDSTrie::from_keys(vec!["tea", "a", "ten", "i", "in", "inn", "to", "ted"])
+    }
+
+    #[test]
+    fn from_keys() {
+        let trie = example();
+        assert_eq!(trie.len(), 8);
+        // root, a, i, t, in, te, to, inn, tea, ted, ten
+        assert_eq!(trie.terminals.len(), 11);
+        assert!(trie.tree.has_sorted_children());
+        let ids: Vec<Option<u64>> = ["a", "i", "in", "to", "inn", "tea", "ted", "ten"]
+            .iter()
+            .map(|key| trie.lookup(key.as_bytes()))
+            .collect();
+        assert_eq!(ids, (0..8).map(Some).collect::<Vec<_>>());
+        assert_eq!(trie.key(8), None);
+    }
+
+    #[test]
+    fn lookup() {
+        let trie = example();
+        assert!(trie.contains(b"ted"));
+        assert!(!trie.contains(b"te"));
+        assert!(!trie.contains(b"teas"));
+        assert!(!trie.contains(b""));
+        assert!(!trie.contains(b"x"));
+        assert_eq!(trie.key(trie.lookup(b"inn").unwrap()).unwrap(), b"inn");
+    }
+
+    #[test]
+    fn empty() {
+        let trie: LOUDSTrie = LOUDSTrie::from_keys(Vec::<&str>::new());
+        assert!(trie.is_empty());
+        assert_eq!(trie.lookup(b""), None);
+        assert_eq!(trie.predictive_search(b"").count(), 0);
+        let trie: LOUDSTrie = LOUDSTrie::from_keys(vec!["", ""]);
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.lookup(b""), Some(0));
+        assert_eq!(trie.key(0).unwrap(), b"");
+        assert_eq!(
+            trie.common_prefix_search(b"abc").collect::<Vec<_>>(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn searches() {
+        let trie = example();
+        let keys = |ids: Vec<u64>| -> Vec<Vec<u8>> {
+            ids.into_iter().map(|id| trie.key(id).unwrap()).collect()
+        };
+        assert_eq!(
+            keys(trie.predictive_search(b"te").collect()),
+            vec![b"tea".to_vec(), b"ted".to_vec(), b"ten".to_vec()]
+        );
+        assert_eq!(trie.predictive_search(b"tex").count(), 0);
+        assert_eq!(trie.predictive_search(b"").count(), 8);
+        assert_eq!(
+            keys(trie.common_prefix_search(b"inner").collect()),
+            vec![b"i".to_vec(), b"in".to_vec(), b"inn".to_vec()]
+        );
+        assert_eq!(trie.common_prefix_search(b"x").count(), 0);
+    }
+
+    #[test]
+    fn save_load() {
+        let trie = example();
+        let path = "testdata/loudstrie.testdata";
+        trie.save_to(path.to_string()).unwrap();
+        let result: LOUDSTrie = LOUDSTrie::from_file(path.to_string()).unwrap();
+        assert_eq!(result.tree, trie.tree);
+        assert_eq!(result.len(), 8);
+        assert_eq!(result.lookup(b"ted"), trie.lookup(b"ted"));
+        let mapped: LOUDSTrie = unsafe { LOUDSTrie::open_mapped(path.to_string()).unwrap() };
+        assert_eq!(mapped.key(5).unwrap(), trie.key(5).unwrap());
+        assert!(LOUDSTree::<u8>::from_file(path.to_string()).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn oracle(
+            keys in prop::collection::vec(prop::collection::vec(0u8..4, 0..6), 0..60),
+            queries in prop::collection::vec(prop::collection::vec(0u8..4, 0..7), 10),
+        ) {
+            let set: BTreeSet<Vec<u8>> = keys.iter().cloned().collect();
+            let trie: LOUDSTrie<PackedLabels<u8>> = LOUDSTrie::from_keys(keys);
+            prop_assert_eq!(trie.len(), set.len() as u64);
+            let mut ids: Vec<u64> = set.iter().map(|key| trie.lookup(key).unwrap()).collect();
+            for id in 0..trie.len() {
+                let key = trie.key(id).unwrap();
+                prop_assert_eq!(trie.lookup(&key), Some(id));
+            }
+            ids.sort();
+            prop_assert_eq!(ids, (0..set.len() as u64).collect::<Vec<_>>());
+            for query in queries {
+                prop_assert_eq!(trie.contains(&query), set.contains(&query));
+                let predicted: Vec<Vec<u8>> = trie
+                    .predictive_search(&query)
+                    .map(|id| trie.key(id).unwrap())
+                    .collect();
+                let expected: Vec<Vec<u8>> =
+                    set.iter().filter(|key| key.starts_with(&query)).cloned().collect();
+                prop_assert_eq!(predicted, expected);
+                let prefixes: Vec<Vec<u8>> = trie
+                    .common_prefix_search(&query)
+                    .map(|id| trie.key(id).unwrap())
+                    .collect();
+                let expected: Vec<Vec<u8>> =
+                    set.iter().filter(|key| query.starts_with(key)).cloned().collect();
+                prop_assert_eq!(prefixes, expected);
+            }
+        }
+    }
+}
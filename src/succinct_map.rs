@@ -0,0 +1,347 @@
+// Copyright 2018 Kevin Kaßelmann, David Mehren, Daniel Rose and Frederik Stehli.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A static map from byte string keys to values. The keys are stored in a `LOUDSTrie`, the
+//! values in an array ordered by the ids of the keys, so a value is found by looking up the
+//! key in the trie and reading the array at its id.
+//!
+//! Example
+//!
+//! ```
+//! # extern crate fp_succinct_trees_1;
+//!
+//! # fn main() {
+//! use fp_succinct_trees_1::succinct_map::SuccinctMap;
+//!
+//! let map: SuccinctMap<String, u32> = vec![
+//!     ("apple".to_string(), 3),
+//!     ("apricot".to_string(), 5),
+//!     ("banana".to_string(), 7),
+//! ]
+//! .into_iter()
+//! .collect();
+//! assert_eq!(map.get("apricot"), Some(&5));
+//! assert_eq!(map.get("cherry"), None);
+//! let keys: Vec<String> = map.get_prefix_iter("ap").map(|(key, _)| key).collect();
+//! assert_eq!(keys, vec!["apple", "apricot"]);
+//! # }
+//! ```
+
+use common::container::SectionKind;
+use common::container::{Container, TreeKind};
+use common::errors::FileError;
+use common::label_store::LabelStore;
+use common::space_usage::{SpaceBreakdown, SpaceUsage};
+use louds_trie::{LOUDSTrie, PredictiveSearch};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+/// Keys of a `SuccinctMap`, which are stored as byte strings.
+pub trait TrieKey {
+    /// Returns the bytes the key is stored as.
+    fn key_bytes(&self) -> &[u8];
+    /// Restores a key from its bytes.
+    fn from_key_bytes(bytes: Vec<u8>) -> Self;
+}
+
+impl TrieKey for Vec<u8> {
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_key_bytes(bytes: Vec<u8>) -> Self {
+        bytes
+    }
+}
+
+impl TrieKey for String {
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    /// Invalid UTF-8, which only occurs in files written by a map with other keys, is
+    /// replaced by U+FFFD.
+    fn from_key_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(key) => key,
+            Err(error) => String::from_utf8_lossy(error.as_bytes()).into_owned(),
+        }
+    }
+}
+
+/// A static map from byte string keys to values. The labels of the trie are kept in a
+/// `LabelStore`, by default a plain `Vec<u8>`.
+pub struct SuccinctMap<K, V, S: LabelStore<u8> = Vec<u8>> {
+    trie: LOUDSTrie<S>,
+    /// The value of every key at the id of the key
+    values: Vec<V>,
+    _key: PhantomData<K>,
+}
+
+impl<K: TrieKey + Debug, V: Debug, S: LabelStore<u8>> Debug for SuccinctMap<K, V, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S: LabelStore<u8>> SpaceUsage for SuccinctMap<K, V, S> {
+    /// The values are counted with their inline size as part of the labels.
+    fn space_usage(&self) -> SpaceBreakdown {
+        self.trie.space_usage() + self.values.space_usage()
+    }
+}
+
+impl<K: TrieKey, V, S: LabelStore<u8>> FromIterator<(K, V)> for SuccinctMap<K, V, S> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        Self::from_pairs(pairs)
+    }
+}
+
+impl<K: TrieKey, V, S: LabelStore<u8>> SuccinctMap<K, V, S> {
+    /// Builds the map of the given pairs, which may be given in any order. If a key occurs
+    /// more than once, the last value is kept.
+    /// # Arguments
+    /// * `pairs` The keys and their values
+    pub fn from_pairs<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let pairs: Vec<(K, V)> = pairs.into_iter().collect();
+        let trie: LOUDSTrie<S> = LOUDSTrie::from_keys(pairs.iter().map(|pair| pair.0.key_bytes()));
+        let mut values: Vec<Option<V>> = (0..trie.len()).map(|_| None).collect();
+        for (key, value) in pairs {
+            values[trie.lookup(key.key_bytes()).unwrap() as usize] = Some(value);
+        }
+        Self {
+            trie,
+            values: values.into_iter().map(Option::unwrap).collect(),
+            _key: PhantomData,
+        }
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> u64 {
+        self.values.len() as u64
+    }
+
+    /// Returns whether the map contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the trie of the keys, whose key ids index the values.
+    pub fn trie(&self) -> &LOUDSTrie<S> {
+        &self.trie
+    }
+
+    /// Returns the value of `key`, or `None` if it is not one of the keys.
+    pub fn get<Q: AsRef<[u8]> + ?Sized>(&self, key: &Q) -> Option<&V> {
+        let id = self.trie.lookup(key.as_ref())?;
+        // not `LabelStore::get`, which is also implemented for `Vec`
+        self.values.as_slice().get(id as usize)
+    }
+
+    /// Returns whether `key` is one of the keys.
+    pub fn contains_key<Q: AsRef<[u8]> + ?Sized>(&self, key: &Q) -> bool {
+        self.trie.contains(key.as_ref())
+    }
+
+    /// Returns all keys and their values in lexicographic order of the keys.
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
+        self.get_prefix_iter(&[])
+    }
+
+    /// Returns all keys that start with `prefix` and their values, in lexicographic order of
+    /// the keys.
+    pub fn get_prefix_iter<Q: AsRef<[u8]> + ?Sized>(&self, prefix: &Q) -> Iter<'_, K, V, S> {
+        Iter {
+            map: self,
+            ids: self.trie.predictive_search(prefix.as_ref()),
+        }
+    }
+}
+
+impl<K: TrieKey, V: Serialize + DeserializeOwned, S: LabelStore<u8>> SuccinctMap<K, V, S> {
+    /// Deserializes a SuccinctMap from a given file
+    /// # Arguments
+    /// * `path` The path of the file to deserialize
+    /// # Errors
+    /// * `WrongTreeKindError` If the file does not contain a SuccinctMap.
+    /// * `InvalidBitvecError` If the stored bits are not a valid LOUDSTree.
+    /// * `InvalidTreeError` If the stored keys are not a valid trie.
+    /// * `MalformedSectionError` If the number of values does not match the number of keys.
+    /// * Every other `FileError` if the file cannot be read or is corrupt.
+    pub fn from_file(path: String) -> Result<Self, FileError> {
+        let container = Container::from_file(&path, TreeKind::Map)?;
+        let trie = LOUDSTrie::from_valid_container(&container)?;
+        let values: Vec<V> = container.values()?;
+        if values.len() as u64 != trie.len() {
+            return Err(FileError::MalformedSectionError(SectionKind::Values));
+        }
+        Ok(Self {
+            trie,
+            values,
+            _key: PhantomData,
+        })
+    }
+
+    /// Serializes a SuccinctMap including its values to a file
+    /// # Arguments
+    /// * `path` The path of the file to save to. Will be overwritten if it exists.
+    pub fn save_to(&self, path: String) -> Result<(), FileError> {
+        let mut writer = self.trie.container_writer(TreeKind::Map)?;
+        writer.add_values(&self.values)?;
+        writer.write_to(&path)
+    }
+}
+
+/// Iterator over keys and their values in lexicographic order of the keys, see
+/// `SuccinctMap::iter` and `SuccinctMap::get_prefix_iter`.
+pub struct Iter<'a, K: 'a, V: 'a, S: LabelStore<u8> + 'a = Vec<u8>> {
+    map: &'a SuccinctMap<K, V, S>,
+    ids: PredictiveSearch<'a, S>,
+}
+
+impl<'a, K: TrieKey, V, S: LabelStore<u8>> Iterator for Iter<'a, K, V, S> {
+    type Item = (K, &'a V);
+
+    fn next(&mut self) -> Option<(K, &'a V)> {
+        let id = self.ids.next()?;
+        let key = K::from_key_bytes(self.map.trie.key(id).unwrap());
+        Some((key, &self.map.values[id as usize]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::label_store::{PackedLabels, WaveletLabels};
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn example() -> SuccinctMap<String, u32> {
+        vec![
+            ("to", 1),
+            ("tea", 2),
+            ("ted", 3),
+            ("ten", 4),
+            ("i", 5),
+            ("in", 6),
+            ("inn", 7),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+    }
+
+    #[test]
+    fn get() {
+        let map = example();
+        assert_eq!(map.len(), 7);
+        assert_eq!(map.get("ted"), Some(&3));
+        assert_eq!(map.get(b"inn"), Some(&7));
+        assert_eq!(map.get("te"), None);
+        assert!(map.contains_key("i"));
+        assert!(!map.contains_key(""));
+        let map: SuccinctMap<Vec<u8>, u8> =
+            SuccinctMap::from_pairs(vec![(vec![1], 1), (vec![], 2), (vec![1], 3)]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&[1]), Some(&3));
+        assert_eq!(map.get(&[]), Some(&2));
+    }
+
+    #[test]
+    fn iter() {
+        let map = example();
+        let pairs: Vec<(String, u32)> = map.iter().map(|(key, value)| (key, *value)).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("i".to_string(), 5),
+                ("in".to_string(), 6),
+                ("inn".to_string(), 7),
+                ("tea".to_string(), 2),
+                ("ted".to_string(), 3),
+                ("ten".to_string(), 4),
+                ("to".to_string(), 1),
+            ]
+        );
+        let values: Vec<u32> = map.get_prefix_iter("te").map(|(_, value)| *value).collect();
+        assert_eq!(values, vec![2, 3, 4]);
+        assert_eq!(map.get_prefix_iter("x").count(), 0);
+        let small: SuccinctMap<String, u32> =
+            SuccinctMap::from_pairs(vec![("b".to_string(), 2), ("a".to_string(), 1)]);
+        assert_eq!(format!("{:?}", small), r#"{"a": 1, "b": 2}"#);
+        let empty: SuccinctMap<String, u32> = SuccinctMap::from_pairs(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(format!("{:?}", empty), "{}");
+    }
+
+    #[test]
+    fn save_load() {
+        let map = example();
+        let path = "testdata/succinctmap.testdata";
+        map.save_to(path.to_string()).unwrap();
+        let result: SuccinctMap<String, u32> = SuccinctMap::from_file(path.to_string()).unwrap();
+        assert_eq!(result.values, map.values);
+        assert_eq!(result.get("ten"), Some(&4));
+        assert!(LOUDSTrie::<Vec<u8>>::from_file(path.to_string()).is_err());
+    }
+
+    #[test]
+    fn label_stores() {
+        let pairs: Vec<(String, u32)> = vec![("tea", 2), ("ted", 3), ("ten", 4), ("inn", 7)]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        let packed: SuccinctMap<String, u32, PackedLabels<u8>> = pairs.iter().cloned().collect();
+        let wavelet: SuccinctMap<String, u32, WaveletLabels<u8>> = pairs.into_iter().collect();
+        assert_eq!(packed.get("ten"), Some(&4));
+        assert_eq!(wavelet.get("inn"), Some(&7));
+        let values: Vec<u32> = wavelet
+            .get_prefix_iter("te")
+            .map(|(_, value)| *value)
+            .collect();
+        assert_eq!(values, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn space_usage() {
+        let map = example();
+        let usage = map.space_usage();
+        assert_eq!(
+            usage,
+            map.trie().space_usage()
+                + SpaceBreakdown {
+                    labels: 7 * 4,
+                    ..SpaceBreakdown::default()
+                }
+        );
+        assert_eq!(usage.labels, map.trie().tree().space_usage().labels + 7 * 4);
+    }
+
+    proptest! {
+        #[test]
+        fn oracle(
+            pairs in prop::collection::vec((prop::collection::vec(0u8..3, 0..5), any::<u16>()), 0..40),
+            prefix in prop::collection::vec(0u8..3, 0..3),
+        ) {
+            let expected: BTreeMap<Vec<u8>, u16> = pairs.iter().cloned().collect();
+            let map: SuccinctMap<Vec<u8>, u16> = pairs.into_iter().collect();
+            prop_assert_eq!(map.len(), expected.len() as u64);
+            for (key, value) in &expected {
+                prop_assert_eq!(map.get(key), Some(value));
+            }
+            let entries: Vec<(Vec<u8>, u16)> = map.iter().map(|(key, value)| (key, *value)).collect();
+            prop_assert_eq!(entries, expected.clone().into_iter().collect::<Vec<_>>());
+            let entries: Vec<(Vec<u8>, u16)> =
+                map.get_prefix_iter(&prefix).map(|(key, value)| (key, *value)).collect();
+            let prefixed: Vec<(Vec<u8>, u16)> =
+                expected.into_iter().filter(|(key, _)| key.starts_with(&prefix)).collect();
+            prop_assert_eq!(entries, prefixed);
+        }
+    }
+}